@@ -0,0 +1,26 @@
+// Times insert/free churn on a big, mostly full pond, with and without a free stack.
+// Run with `cargo bench --bench churn`.
+use lilypads::Pond;
+use std::hint::black_box;
+use std::time::Instant;
+
+const SLOTS: usize = 1_000_000;
+const ROUNDS: usize = 1_000_000;
+
+fn churn(mut pool: Pond<u64>) -> std::time::Duration {
+  for val in 0 .. SLOTS as u64 { let _ = pool.insert(val); }
+  // Scatter the frees so the bitmap can't just look at the front
+  let mut idx = 12_345;
+  let start = Instant::now();
+  for round in 0 .. ROUNDS {
+    idx = (idx * 7_919 + 13) % SLOTS;
+    pool.free(idx);
+    idx = black_box(pool.insert(round as u64));
+  }
+  start.elapsed()
+}
+
+fn main() {
+  println!("bitmap:     {:?} per insert/free", churn(Pond::new()) / ROUNDS as u32);
+  println!("free stack: {:?} per insert/free", churn(Pond::with_free_stack()) / ROUNDS as u32);
+}
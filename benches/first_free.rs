@@ -0,0 +1,22 @@
+// Times how long finding the only free slot near the end of a huge pond takes.
+// Compare `cargo bench --bench first_free` against `cargo bench --bench first_free --features simd`.
+use lilypads::Pond;
+use std::hint::black_box;
+use std::time::Instant;
+
+const SLOTS: usize = 4_000_000;
+const RUNS: u32 = 200;
+
+fn main() {
+  // The fewer layers, the longer the top layer which has to be scanned
+  for layers in [0, 1] {
+    let mut pool = Pond::with_layers(layers);
+    pool.resize(SLOTS);
+    for idx in 0 .. SLOTS { pool.write(idx, ()); }
+    pool.free(SLOTS - 100);
+
+    let start = Instant::now();
+    for _ in 0 .. RUNS { assert_eq!(black_box(&pool).next_index(), SLOTS - 100) }
+    println!("{layers} layers: {:?} per search", start.elapsed() / RUNS);
+  }
+}
@@ -0,0 +1,20 @@
+// Times a tight run of inserts into an empty pond, and into the holes of a pond which was just emptied.
+// Run with `cargo bench --bench insert`.
+use lilypads::Pond;
+use std::hint::black_box;
+use std::time::Instant;
+
+const SLOTS: usize = 4_000_000;
+
+fn main() {
+  let mut pool = Pond::new();
+  let start = Instant::now();
+  for val in 0 .. SLOTS as u64 { black_box(pool.insert(val)); }
+  println!("fresh:  {:?} per insert", start.elapsed() / SLOTS as u32);
+
+  // Every slot is free but already allocated, so this is all bitmap work
+  for idx in 0 .. SLOTS { pool.free(idx); }
+  let start = Instant::now();
+  for val in 0 .. SLOTS as u64 { black_box(pool.insert(val)); }
+  println!("refill: {:?} per insert", start.elapsed() / SLOTS as u32);
+}
@@ -0,0 +1,23 @@
+// Times one pass of iter and iter_mut over a huge pond with only a few occupied slots.
+use lilypads::Pond;
+use std::hint::black_box;
+use std::time::Instant;
+
+const SLOTS: usize = 10_000_000;
+const RUNS: u32 = 20;
+
+fn main() {
+  let mut pool = Pond::new();
+  pool.resize(SLOTS);
+  for idx in [0, SLOTS / 2, SLOTS - 1] { pool.write(idx, idx as u64); }
+
+  let start = Instant::now();
+  for _ in 0 .. RUNS { assert_eq!(black_box(&pool).iter().count(), 3) }
+  println!("iter:     {:?} per pass", start.elapsed() / RUNS);
+
+  let start = Instant::now();
+  for _ in 0 .. RUNS {
+    for (_, val) in black_box(&mut pool).iter_mut() { *val += 1 }
+  }
+  println!("iter_mut: {:?} per pass", start.elapsed() / RUNS);
+}
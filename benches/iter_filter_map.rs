@@ -0,0 +1,32 @@
+// Times a filter_map pass over a huge, sparse pond, through iter and through iter_filter_map.
+use lilypads::Pond;
+use std::hint::black_box;
+use std::time::Instant;
+
+const SLOTS: usize = 10_000_000;
+const SPACING: usize = 1000;
+const RUNS: u32 = 20;
+
+// Keeps every other value
+fn pick(idx: usize, val: &u64) -> Option<u64> { (idx / SPACING).is_multiple_of(2).then_some(*val) }
+
+fn main() {
+  let mut pool = Pond::new();
+  pool.resize(SLOTS);
+  for idx in (0 .. SLOTS).step_by(SPACING) { pool.write(idx, idx as u64); }
+  let expected: u64 = (0 .. SLOTS as u64).step_by(2 * SPACING).sum();
+
+  let start = Instant::now();
+  for _ in 0 .. RUNS {
+    let sum: u64 = black_box(&pool).iter().filter_map(|(idx, val)| pick(idx, val)).sum();
+    assert_eq!(sum, expected);
+  }
+  println!("iter().filter_map: {:?} per pass", start.elapsed() / RUNS);
+
+  let start = Instant::now();
+  for _ in 0 .. RUNS {
+    let sum: u64 = black_box(&pool).iter_filter_map(pick).sum();
+    assert_eq!(sum, expected);
+  }
+  println!("iter_filter_map:   {:?} per pass", start.elapsed() / RUNS);
+}
@@ -0,0 +1,56 @@
+// Measures how many heap bytes a pond holds onto, counted at the allocator.
+// Mostly interesting for workloads with thousands of tiny ponds, where the bitmap overhead dominates.
+use lilypads::Pond;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    LIVE.fetch_add(layout.size(), Ordering::Relaxed);
+    unsafe { System.alloc(layout) }
+  }
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    unsafe { System.dealloc(ptr, layout) }
+  }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// Heap bytes still held by whatever `build` returns
+fn held<P>(build: impl FnOnce() -> P) -> usize {
+  let before = LIVE.load(Ordering::Relaxed);
+  let pond = black_box(build());
+  let bytes = LIVE.load(Ordering::Relaxed) - before;
+  drop(pond);
+  bytes
+}
+
+fn main() {
+  let empty = held(Pond::<u32>::new);
+  let one = held(|| {
+    let mut pool = Pond::<u32>::new();
+    let _ = pool.insert(0);
+    pool
+  });
+  let thousand = held(|| (0 .. 1000).map(|val| {
+    let mut pool = Pond::<u32>::new();
+    let _ = pool.insert(val);
+    pool
+  }).collect::<Vec<_>>());
+  let million = held(|| {
+    let mut pool = Pond::<u32>::new();
+    pool.resize(1_000_000);
+    pool
+  });
+  println!("empty pond: {empty} bytes");
+  println!("one slot: {one} bytes");
+  // The Vec holding them counts too, so take the inline pond structs back out
+  println!("1000 one slot ponds: {} bytes per pond", thousand / 1000 - size_of::<Pond<u32>>());
+  println!("1M slots: {million} bytes, {} of them slot data", 1_000_000 * size_of::<u32>());
+}
@@ -1,4 +1,7 @@
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::TryReserveError;
+use std::ops::Range;
 
 // Given an index, the first 6 bits represents the offset in self.base[box] to find the packed bit.
 // This is because it takes 6 bits to represent any bit of the u64 (0 - 63)
@@ -15,63 +18,213 @@ const UNSET_FULL: u64 = !0 << 32; // SECOND 32 BITS
 const SET_FULL: u64 = !0 >> 32; // FIRST 32 BITS
 
 // First 32 bits of accel_layers are full_tracking, second 32 are empty_tracking
-#[derive(Deserialize, Serialize, Debug)]
+// Acceleration layers are only built once the layer beneath them spans more than a single word,
+// so small bitmaps are nothing more than their base words.
+#[derive(Debug, Clone)]
 pub struct AcceleratedBitmap {
   base: Vec<u64>,
   accel_layers: Vec< Vec<u64> >,
+  max_layers: usize,
 }
 impl AcceleratedBitmap {
 
   pub fn new(layers: usize) -> Self {
-    let mut accel_layers = Vec::new();
-    accel_layers.resize_with(layers, Vec::new);
-
     Self { 
       base: Vec::new(),
-      accel_layers,
+      accel_layers: Vec::new(),
+      max_layers: layers,
     }
   }
 
+  /// Builds a bitmap covering every bit of `words`, summarizing them in a single pass.
+  pub fn from_words(words: Vec<u64>, layers: usize) -> Self {
+    let mut bitmap = Self::new(layers);
+    bitmap.rebuild_from_base(words);
+    bitmap
+  }
+
+  /// Replaces the base with `base`, then recomputes every acceleration layer bottom-up in one pass.
+  /// Much cheaper than [AcceleratedBitmap::set]ting each bit when restoring a whole bitmap.
+  pub fn rebuild_from_base(&mut self, base: Vec<u64>) {
+    self.base = base;
+    self.accel_layers.clear();
+    self.resize(self.base.len() << BASE_SHIFT);
+  }
+
   pub fn resize(&mut self, size: usize) {
-    let offset = size & BASE_MASK;
-    let mut full_word_count= size >> BASE_SHIFT;
-    self.base.resize(full_word_count + 1, 0);
+    let old_words = self.base.len();
+    let words = size.div_ceil(64);
+    self.base.resize(words, 0);
     // This line zeros any leftovers after the requested size
     // It generates a bitstring of 1s via not
     // Creates 0s in the front via shift
     // Inverts the string via not
-    self.base[full_word_count] &= !(!0 << offset);
+    let offset = size & BASE_MASK;
+    let mut trimmed = false;
+    if offset != 0 {
+      let kept = self.base[words - 1] & !(!0 << offset);
+      trimmed = kept != self.base[words - 1];
+      self.base[words - 1] = kept;
+    }
+    // Only keep as many layers as it takes to summarize the base into a single word
+    let mut layer_count = 0;
+    let mut child_len = words;
+    while child_len > 1 && layer_count < self.max_layers {
+      child_len = child_len.div_ceil(32);
+      layer_count += 1;
+    }
+    // Moving the end around inside the last word without clearing anything leaves the layers as they were,
+    // which keeps appending a slot at a time cheap
+    if words == old_words && !trimmed && layer_count == self.accel_layers.len() { return }
+
+    let existing = self.accel_layers.len();
+    self.accel_layers.truncate(layer_count);
+    self.accel_layers.resize_with(layer_count, Vec::new);
+
+    // Everything from the last shared word onwards may have changed, new layers need a full rebuild
+    let mut start = old_words.min(words).saturating_sub(1);
+    for layer in 0 .. layer_count {
+      if layer >= existing { start = 0 }
+      start = self.refresh_layer(layer, start);
+    }
+  }
+
+  /// Tries to reserve room for `size` bits in total, across the base and any existing layers.
+  pub fn try_reserve(&mut self, size: usize) -> Result<(), TryReserveError> {
+    let mut words = size.div_ceil(64);
+    self.base.try_reserve(words.saturating_sub(self.base.len()))?;
     for layer in &mut self.accel_layers {
-      let offset = size & ACCEL_MASK;
-      full_word_count >>= ACCEL_SHIFT;
-      layer.resize(size + 1, 0);
-      // See above, except now we need to do this for first 32 bits and second 32 bits individually
-      let set_mask = SET_FULL >> (32 - offset);
-      let unset_mask = UNSET_FULL >> (32 - offset) & UNSET_FULL;
-      layer[full_word_count] &= unset_mask | set_mask;
+      words = words.div_ceil(32);
+      layer.try_reserve(words.saturating_sub(layer.len()))?;
     }
+    Ok(())
   }
 
-  pub fn first_free(&self) -> Option<usize> {
-    let mut idx = {
-      let mut result = None;
-      for (val, boks) in self.accel_layers.last().unwrap().iter().enumerate() {
-        if *boks != SET_FULL {
-          result = Some((val << ACCEL_SHIFT) + boks.trailing_ones() as usize);
-          break;
-        }
+  /// Releases any capacity the words no longer need.
+  pub fn shrink_to_fit(&mut self) {
+    self.base.shrink_to_fit();
+    for layer in &mut self.accel_layers { layer.shrink_to_fit() }
+    self.accel_layers.shrink_to_fit();
+  }
+
+  /// Returns the heap bytes held by the base and acceleration layers.
+  pub fn memory_usage(&self) -> usize {
+    let words = self.base.capacity() + self.accel_layers.iter().map(Vec::capacity).sum::<usize>();
+    words * size_of::<u64>() + self.accel_layers.capacity() * size_of::<Vec<u64>>()
+  }
+
+  /// Recomputes `layer` from the layer beneath it, starting at the word summarizing `child_start`.
+  /// Returns the first word of `layer` which was recomputed.
+  fn refresh_layer(&mut self, layer: usize, child_start: usize) -> usize {
+    let (below, above) = self.accel_layers.split_at_mut(layer);
+    let (children, is_base) = match below.last() {
+      Some(children) => (children, false),
+      None => (&self.base, true),
+    };
+    let parent = &mut above[0];
+    parent.resize(children.len().div_ceil(32), 0);
+    let parent_start = child_start >> ACCEL_SHIFT;
+    for (word, summary) in parent.iter_mut().enumerate().skip(parent_start) {
+      // Children past the end don't exist, so they're never full and always empty
+      *summary = UNSET_FULL;
+      for (offset, child) in children[word << ACCEL_SHIFT ..].iter().take(32).enumerate() {
+        let bit = 1 << offset;
+        let (is_full, is_empty) = word_state(*child, is_base);
+        if is_full { *summary |= bit }
+        if !is_empty { *summary &= !(bit << 32) }
       }
-      result?
+    }
+    parent_start
+  }
+
+  /// Returns the first unset bit, which may sit past the size requested by [AcceleratedBitmap::resize]
+  /// if every slot before it is set. Returns None if every bit in every word is set.
+  pub fn first_free(&self) -> Option<usize> {
+    let level = self.accel_layers.len();
+    let node = match self.accel_layers.last() {
+      Some(top) => first_not_full(top, SET_FULL)?,
+      None => first_not_full(&self.base, u64::MAX)?,
     };
+    self.descend(node, level)
+  }
+
+  /// Returns the first unset bit at or after `start`, with the same caveats as [AcceleratedBitmap::first_free].
+  pub fn first_free_from(&self, start: usize) -> Option<usize> {
+    let mut node = start >> BASE_SHIFT;
+    // Pretend everything before start is set
+    let word = *self.base.get(node)? | !(!0 << (start & BASE_MASK));
+    if word != u64::MAX { return Some( (node << BASE_SHIFT) + word.trailing_ones() as usize ) }
+
+    // Climb until a parent has a non-full child after the one we came from
+    for (level, layer) in self.accel_layers.iter().enumerate() {
+      let offset = node & ACCEL_MASK;
+      node >>= ACCEL_SHIFT;
+      let full = layer[node] as u32 | (!0 >> (31 - offset));
+      if full != u32::MAX { return self.descend((node << ACCEL_SHIFT) + full.trailing_ones() as usize, level) }
+    }
+
+    // Nothing left under the parents we climbed through, so continue along the top
+    let level = self.accel_layers.len();
+    let node = match self.accel_layers.last() {
+      Some(top) => first_not_full(&top[node + 1 ..], SET_FULL)?,
+      None => first_not_full(&self.base[node + 1 ..], u64::MAX)?,
+    } + node + 1;
+    self.descend(node, level)
+  }
 
-    let mut iter = self.accel_layers.iter().rev();
-    iter.next();
-    for layer in iter {
-      let offset = (layer[idx] as u32).trailing_ones() as usize;
-      idx = (idx << ACCEL_SHIFT) + offset;
+  /// Returns the first unset bit whose index is a multiple of `align`, with the same caveats as [AcceleratedBitmap::first_free].
+  /// Panics if `align` is 0.
+  pub fn first_free_aligned(&self, align: usize) -> Option<usize> {
+    let mut start = 0;
+    loop {
+      let aligned = self.first_free_from(start)?.next_multiple_of(align);
+      if aligned >= self.base.len() << BASE_SHIFT || !self.is_set(aligned) { return Some(aligned) }
+      start = aligned + 1;
     }
-    let offset = (self.base[idx] as u32).trailing_ones() as usize;
-    Some( (idx << BASE_SHIFT) + offset )
+  }
+
+  // Follows the first non-full child down from a word in `level` (0 being the base) to a free bit.
+  // Running off the end of a layer means every word which does exist is full.
+  fn descend(&self, mut node: usize, level: usize) -> Option<usize> {
+    for layer in self.accel_layers[.. level].iter().rev() {
+      let offset = (*layer.get(node)? as u32).trailing_ones() as usize;
+      node = (node << ACCEL_SHIFT) + offset;
+    }
+    let offset = self.base.get(node)?.trailing_ones() as usize;
+    Some( (node << BASE_SHIFT) + offset )
+  }
+
+  /// Returns the first set bit at or after `start`.
+  pub fn first_set_from(&self, start: usize) -> Option<usize> {
+    let mut node = start >> BASE_SHIFT;
+    // Pretend everything before start is unset
+    let word = *self.base.get(node)? & (!0 << (start & BASE_MASK));
+    if word != 0 { return Some( (node << BASE_SHIFT) + word.trailing_zeros() as usize ) }
+
+    // Climb until a parent has a non-empty child after the one we came from
+    for (level, layer) in self.accel_layers.iter().enumerate() {
+      let offset = node & ACCEL_MASK;
+      node >>= ACCEL_SHIFT;
+      let empty = (layer[node] >> 32) as u32 | (!0 >> (31 - offset));
+      if empty != u32::MAX { return self.descend_set((node << ACCEL_SHIFT) + empty.trailing_ones() as usize, level) }
+    }
+
+    let level = self.accel_layers.len();
+    let node = match self.accel_layers.last() {
+      Some(top) => top.iter().skip(node + 1).position(|boks| (boks >> 32) as u32 != u32::MAX)?,
+      None => self.base.iter().skip(node + 1).position(|word| *word != 0)?,
+    } + node + 1;
+    self.descend_set(node, level)
+  }
+
+  // Follows the first non-empty child down from a word in `level` to a set bit.
+  // Missing children are always empty, so this never runs off the end of a layer.
+  fn descend_set(&self, mut node: usize, level: usize) -> Option<usize> {
+    for layer in self.accel_layers[.. level].iter().rev() {
+      let offset = ((layer[node] >> 32) as u32).trailing_ones() as usize;
+      node = (node << ACCEL_SHIFT) + offset;
+    }
+    Some( (node << BASE_SHIFT) + self.base[node].trailing_zeros() as usize )
   }
 
   /// Panics if out of bound attempt
@@ -80,9 +233,21 @@ impl AcceleratedBitmap {
     let bit = 1 << offset;
     idx >>= BASE_SHIFT;
     if value { self.base[idx] |= bit } else { self.base[idx] &= !bit }
-    
-    let mut is_full = self.base[idx] == u64::MAX;
-    let mut is_empty = self.base[idx] == 0;
+    self.propagate(idx);
+  }
+
+  /// Unsets every bit which is set in `mask`, a word at a time. Words past the end of the bitmap are ignored.
+  pub fn clear_mask(&mut self, mask: &[u64]) {
+    for (idx, mask) in mask.iter().enumerate().take(self.base.len()) {
+      if self.base[idx] & mask == 0 { continue }
+      self.base[idx] &= !mask;
+      self.propagate(idx);
+    }
+  }
+
+  // Walks the state of base word `idx` up through the acceleration layers
+  fn propagate(&mut self, mut idx: usize) {
+    let (mut is_full, mut is_empty) = word_state(self.base[idx], true);
 
     for layer in &mut self.accel_layers {
       let offset = idx & ACCEL_MASK;
@@ -90,23 +255,340 @@ impl AcceleratedBitmap {
       idx >>= ACCEL_SHIFT;
       if is_full { layer[idx] |= bit } else { layer[idx] &= !bit }
       if is_empty { layer[idx] |= bit << 32 } else { layer[idx] &= !(bit << 32) }
-      is_full = layer[idx] & SET_FULL == SET_FULL;
-      is_empty = layer[idx] & UNSET_FULL == UNSET_FULL;
+      (is_full, is_empty) = word_state(layer[idx], false);
     }
   }
 
+  /// [AcceleratedBitmap::set]s the bit, returning what it used to be. The layers are only walked if the bit changes.
+  /// Panics if out of bound attempt
+  pub fn replace(&mut self, idx: usize, value: bool) -> bool {
+    let previous = self.is_set(idx);
+    if previous != value { self.set(idx, value) }
+    previous
+  }
+
   // Panics if out of bound attempt
   pub fn is_set(&self, idx: usize) -> bool {
     let offset = idx & BASE_MASK;
     0 != (self.base[idx >> BASE_SHIFT] & (1 << offset))
   }
 
+  /// Checks that this bitmap is a consistent view of `size` slots, describing the first problem found.
+  /// Rebuilds every acceleration layer from scratch, so it's slow.
+  pub fn validate(&self, size: usize) -> Result<(), String> {
+    let words = size.div_ceil(64);
+    if self.base.len() != words {
+      return Err(format!("bitmap holds {} words but {size} slots need {words}", self.base.len()))
+    }
+    let offset = size & BASE_MASK;
+    if offset != 0 && self.base[words - 1] & (!0 << offset) != 0 {
+      return Err(format!("bits are set past the end of the {size} slots"))
+    }
+
+    let mut rebuilt = Self { base: self.base.clone(), accel_layers: Vec::new(), max_layers: self.max_layers };
+    rebuilt.resize(size);
+    if rebuilt.accel_layers.len() != self.accel_layers.len() {
+      return Err(format!("bitmap has {} acceleration layers, expected {}", self.accel_layers.len(), rebuilt.accel_layers.len()))
+    }
+    let zipped = rebuilt.accel_layers.iter().zip(&self.accel_layers);
+    if let Some(layer) = zipped.into_iter().position(|(expected, actual)| expected != actual) {
+      return Err(format!("acceleration layer {layer} doesn't summarize the layer beneath it"))
+    }
+
+    let linear = (0 .. size).find(|idx| !self.is_set(*idx));
+    let first_free = self.first_free().filter(|idx| *idx < size);
+    if first_free != linear {
+      return Err(format!("first_free found {first_free:?} but a linear scan found {linear:?}"))
+    }
+    Ok(())
+  }
+
+  /// Returns true if every bit in `range` is set. Bits past the end of the bitmap count as unset.
+  pub fn all_set(&self, range: Range<usize>) -> bool {
+    if range.start < range.end && range.end > self.base.len() << BASE_SHIFT { return false }
+    self.masked_words(range).all(|(word, mask)| word == mask)
+  }
+
+  /// Returns true if any bit in `range` is set.
+  pub fn any_set(&self, range: Range<usize>) -> bool {
+    self.masked_words(range).any(|(word, _)| word != 0)
+  }
+
+  // Yields every word overlapping `range` with the bits outside of it masked off, alongside the mask.
+  // Only covers the words which exist, everything past them is unset anyway.
+  fn masked_words(&self, range: Range<usize>) -> impl Iterator<Item = (u64, u64)> + '_ {
+    let (start, end) = (range.start, range.end.min(self.base.len() << BASE_SHIFT));
+    let first = start >> BASE_SHIFT;
+    let last = end.saturating_sub(1) >> BASE_SHIFT;
+    let words = if start < end { first .. last + 1 } else { 0 .. 0 };
+    words.map(move |idx| {
+      let mut mask = !0;
+      if idx == first { mask &= !0 << (start & BASE_MASK) }
+      if idx == last { mask &= !0 >> (BASE_MASK - ((end - 1) & BASE_MASK)) }
+      (self.base[idx] & mask, mask)
+    })
+  }
+
+  /// Returns the highest set bit, if any. Follows the empty-tracking down from the top layer rather than scanning.
+  pub fn last_set(&self) -> Option<usize> {
+    let node = match self.accel_layers.last() {
+      Some(top) => top.iter().rposition(|boks| (boks >> 32) as u32 != u32::MAX)?,
+      None => self.base.iter().rposition(|word| *word != 0)?,
+    };
+    Some( self.descend_last(node, self.accel_layers.len()) )
+  }
+
+  /// Returns the highest set bit below `end`, if any. Climbs and descends the empty-tracking like [AcceleratedBitmap::first_set_from].
+  pub fn last_set_before(&self, end: usize) -> Option<usize> {
+    let end = end.min(self.base.len() << BASE_SHIFT);
+    if end == 0 { return None }
+    let mut node = (end - 1) >> BASE_SHIFT;
+    // Pretend everything from end onwards is unset
+    let word = self.base[node] & (!0 >> (BASE_MASK - ((end - 1) & BASE_MASK)));
+    if word != 0 { return Some( (node << BASE_SHIFT) + BASE_MASK - word.leading_zeros() as usize ) }
+
+    // Climb until a parent has a non-empty child before the one we came from
+    for (level, layer) in self.accel_layers.iter().enumerate() {
+      let offset = node & ACCEL_MASK;
+      node >>= ACCEL_SHIFT;
+      let filled = !(layer[node] >> 32) as u32 & !(u32::MAX << offset);
+      if filled != 0 { return Some( self.descend_last((node << ACCEL_SHIFT) + ACCEL_MASK - filled.leading_zeros() as usize, level) ) }
+    }
+
+    let level = self.accel_layers.len();
+    let node = match self.accel_layers.last() {
+      Some(top) => top[.. node].iter().rposition(|boks| (boks >> 32) as u32 != u32::MAX)?,
+      None => self.base[.. node].iter().rposition(|word| *word != 0)?,
+    };
+    Some( self.descend_last(node, level) )
+  }
+
+  // Follows the last non-empty child down from a word in `level` to the highest set bit beneath it.
+  // Missing children are always empty, so the highest non-empty child always exists.
+  fn descend_last(&self, mut node: usize, level: usize) -> usize {
+    for layer in self.accel_layers[.. level].iter().rev() {
+      let filled = !(layer[node] >> 32) as u32;
+      node = (node << ACCEL_SHIFT) + ACCEL_MASK - filled.leading_zeros() as usize;
+    }
+    (node << BASE_SHIFT) + BASE_MASK - self.base[node].leading_zeros() as usize
+  }
+
+  /// Counts every set bit.
+  pub fn count_set(&self) -> usize { self.base.iter().map(|word| word.count_ones() as usize).sum() }
+
+  /// Counts the set bits within `range`.
+  pub fn count_set_in(&self, range: Range<usize>) -> usize {
+    self.masked_words(range).map(|(word, _)| word.count_ones() as usize).sum()
+  }
+
+  /// Walks every set bit in ascending order (or descending, through [DoubleEndedIterator]),
+  /// skipping unset runs a word at a time.
+  pub fn iter_set(&self) -> SetBits<'_> {
+    SetBits { words: &self.base, start: 0, front: (0, 0), back: (0, 0) }
+  }
+
+  /// Like [AcceleratedBitmap::iter_set], but skips every bit before `start`.
+  pub fn iter_set_from(&self, start: usize) -> SetBits<'_> {
+    let word = start >> BASE_SHIFT;
+    let Some(first) = self.base.get(word) else { return SetBits { words: &[], start: 0, front: (0, 0), back: (0, 0) } };
+    let front = (word << BASE_SHIFT, first & (!0 << (start & BASE_MASK)));
+    SetBits { words: &self.base[word + 1 ..], start: word + 1, front, back: (0, 0) }
+  }
+
+  /// Walks every set bit in descending order, using `leading_zeros` to skip unset runs.
+  pub fn iter_set_rev(&self) -> std::iter::Rev<SetBits<'_>> { self.iter_set().rev() }
+
+  /// The raw base words, packed right to left.
+  pub fn words(&self) -> &[u64] { &self.base }
+
+  /// Gives up the base words, dropping the layers.
+  pub fn into_words(self) -> Vec<u64> { self.base }
+
+  /// The most acceleration layers this bitmap will build.
+  #[cfg(feature = "serde")]
+  pub fn max_layers(&self) -> usize { self.max_layers }
+
+}
+
+/// Yields the index of every set bit across `words`, in ascending order.
+pub fn set_bits(words: impl Iterator<Item = u64>) -> impl Iterator<Item = usize> {
+  words.enumerate().flat_map(|(idx, mut word)| {
+    std::iter::from_fn(move || {
+      if word == 0 { return None }
+      let offset = word.trailing_zeros() as usize;
+      // Clears the lowest set bit
+      word &= word - 1;
+      Some( (idx << BASE_SHIFT) + offset )
+    })
+  })
+}
+
+// The acceleration layers can be rebuilt from the base, so only the base and how many layers to build are serialized
+#[cfg(feature = "serde")]
+#[derive(Deserialize, Serialize)]
+#[serde(rename = "AcceleratedBitmap")]
+struct CompactBitmap<B> {
+  base: B,
+  max_layers: usize,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for AcceleratedBitmap {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    CompactBitmap { base: &self.base[..], max_layers: self.max_layers }.serialize(serializer)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AcceleratedBitmap {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let compact = CompactBitmap::<Vec<u64>>::deserialize(deserializer)?;
+    let mut bitmap = Self::new(compact.max_layers);
+    bitmap.rebuild_from_base(compact.base);
+    Ok(bitmap)
+  }
+}
+
+/// Iterator over the set bits of an [AcceleratedBitmap], see [AcceleratedBitmap::iter_set].
+pub struct SetBits<'a> {
+  // Words neither end has reached yet, the first of which is word `start`
+  words: &'a [u64],
+  start: usize,
+  // The bits either end hasn't yielded from the word it's on, alongside the index of that word's first bit
+  front: (usize, u64),
+  back: (usize, u64),
+}
+impl Iterator for SetBits<'_> {
+  type Item = usize;
+  fn next(&mut self) -> Option<usize> {
+    while self.front.1 == 0 {
+      if let Some((word, rest)) = self.words.split_first() {
+        self.front = (self.start << BASE_SHIFT, *word);
+        self.words = rest;
+        self.start += 1;
+      } else {
+        // Only the back's word is left
+        self.front = std::mem::take(&mut self.back);
+        if self.front.1 == 0 { return None }
+      }
+    }
+    let (first, word) = &mut self.front;
+    let offset = word.trailing_zeros() as usize;
+    // Clears the lowest set bit
+    *word &= *word - 1;
+    Some( *first + offset )
+  }
+}
+impl DoubleEndedIterator for SetBits<'_> {
+  fn next_back(&mut self) -> Option<usize> {
+    while self.back.1 == 0 {
+      if let Some((word, rest)) = self.words.split_last() {
+        self.back = ((self.start + rest.len()) << BASE_SHIFT, *word);
+        self.words = rest;
+      } else {
+        self.back = std::mem::take(&mut self.front);
+        if self.back.1 == 0 { return None }
+      }
+    }
+    let (first, word) = &mut self.back;
+    let offset = BASE_MASK - word.leading_zeros() as usize;
+    *word &= !(1 << offset);
+    Some( *first + offset )
+  }
+}
+
+// Returns the first word missing any of the bits in `full`, which is how the top layer gets scanned for free space
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+fn first_not_full(words: &[u64], full: u64) -> Option<usize> { words.iter().position(|word| word & full != full) }
+
+// Same as above, but ANDs together 8 words at a time with SSE2 (which every x86_64 cpu has) so full runs are skipped quickly
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+fn first_not_full(words: &[u64], full: u64) -> Option<usize> {
+  use std::arch::x86_64::*;
+  let chunks = words.chunks_exact(8);
+  let tail = chunks.remainder();
+  for (chunk_idx, chunk) in chunks.enumerate() {
+    let ptr = chunk.as_ptr() as *const __m128i;
+    // Unaligned loads of the 4 pairs of words in the chunk, which chunks_exact guarantees are in bounds
+    let all_full = unsafe {
+      let wanted = _mm_set1_epi64x(full as i64);
+      let all = _mm_and_si128(
+        _mm_and_si128(_mm_loadu_si128(ptr), _mm_loadu_si128(ptr.add(1))),
+        _mm_and_si128(_mm_loadu_si128(ptr.add(2)), _mm_loadu_si128(ptr.add(3))),
+      );
+      _mm_movemask_epi8(_mm_cmpeq_epi8(_mm_and_si128(all, wanted), wanted)) == 0xFFFF
+    };
+    if !all_full {
+      return chunk.iter().position(|word| word & full != full).map(|offset| (chunk_idx << 3) + offset)
+    }
+  }
+  tail.iter().position(|word| word & full != full).map(|offset| words.len() - tail.len() + offset)
+}
+
+// Returns whether a word is (full, empty), reading accel words as packed tracking bits
+fn word_state(word: u64, is_base: bool) -> (bool, bool) {
+  if is_base { (word == u64::MAX, word == 0) }
+  else { (word & SET_FULL == SET_FULL, word & UNSET_FULL == UNSET_FULL) }
 }
 
 
 #[cfg(test)]
 mod tests {
-  use super::AcceleratedBitmap;
+  use super::{AcceleratedBitmap, set_bits};
+
+  #[test]
+  fn set_bit_iteration() {
+    let mut tree = AcceleratedBitmap::new(2);
+    tree.resize(200);
+    for idx in [0, 5, 63, 64, 130, 199] { tree.set(idx, true) }
+    let set: Vec<_> = set_bits(tree.words().iter().copied()).collect();
+    assert_eq!(set, vec![0, 5, 63, 64, 130, 199]);
+  }
+
+  #[test]
+  fn iter_set() {
+    let mut tree = AcceleratedBitmap::new(2);
+    tree.resize(64 * 5);
+    let set = [0, 5, 63, 64, 130, 255, 256, 319];
+    for idx in set { tree.set(idx, true) }
+    assert_eq!(tree.iter_set().collect::<Vec<_>>(), set);
+
+    // Meeting in the middle, including inside a shared word
+    let mut iter = tree.iter_set();
+    assert_eq!((iter.next(), iter.next_back(), iter.next_back()), (Some(0), Some(319), Some(256)));
+    assert_eq!(iter.collect::<Vec<_>>(), [5, 63, 64, 130, 255]);
+    let mut iter = tree.iter_set();
+    for _ in 0 .. 6 { iter.next_back(); }
+    assert_eq!((iter.next(), iter.next(), iter.next()), (Some(0), Some(5), None));
+    assert_eq!(iter.next_back(), None);
+  }
+
+  #[test]
+  fn iter_set_from() {
+    let mut tree = AcceleratedBitmap::new(2);
+    tree.resize(64 * 5);
+    let set = [0, 5, 63, 64, 130, 255, 256, 319];
+    for idx in set { tree.set(idx, true) }
+    for start in 0 .. 64 * 6 {
+      let expected: Vec<_> = set.iter().copied().filter(|idx| *idx >= start).collect();
+      assert_eq!(tree.iter_set_from(start).collect::<Vec<_>>(), expected);
+      assert_eq!(tree.iter_set_from(start).rev().collect::<Vec<_>>(), expected.into_iter().rev().collect::<Vec<_>>());
+    }
+  }
+
+  #[test]
+  fn iter_set_rev() {
+    let patterns: [&[usize]; 4] = [&[], &[0], &[63, 64], &[1, 2, 3, 100, 127, 128, 500, 1000, 1023]];
+    for pattern in patterns {
+      let mut tree = AcceleratedBitmap::new(2);
+      tree.resize(1024);
+      for idx in pattern { tree.set(*idx, true) }
+      let mut forward: Vec<_> = tree.iter_set().collect();
+      forward.reverse();
+      assert_eq!(tree.iter_set_rev().collect::<Vec<_>>(), forward);
+    }
+  }
+
   #[test]
   fn write() {
     let mut tree = AcceleratedBitmap::new(2);
@@ -115,13 +597,13 @@ mod tests {
     tree.set(0, true);
     // Does setting work correctly
     tree.set(1, true);
-    assert_eq!(tree.is_set(1), true);
+    assert!(tree.is_set(1));
 
     // Make sure setting and unsetting work
     tree.set(2, true);
-    assert_eq!(tree.is_set(2), true);
+    assert!(tree.is_set(2));
     tree.set(2, false);
-    assert_eq!(tree.is_set(2), false);
+    assert!(!tree.is_set(2));
     
     tree.set(1, false);
 
@@ -135,7 +617,214 @@ mod tests {
     tree.resize(63);
     tree.set(62, true);
     tree.resize(64);
-    assert_eq!(tree.is_set(62), true);
+    assert!(tree.is_set(62));
+  }
+
+  #[test]
+  fn first_free_from() {
+    let mut tree = AcceleratedBitmap::new(2);
+    tree.resize(64 * 1100);
+    for idx in 0 .. 64 * 1100 { tree.set(idx, true) }
+    tree.set(3, false);
+    tree.set(64 * 40 + 7, false);
+    tree.set(64 * 1050, false);
+    assert_eq!(tree.first_free_from(0), Some(3));
+    assert_eq!(tree.first_free_from(3), Some(3));
+    // Skips whole words and has to climb through the layers
+    assert_eq!(tree.first_free_from(4), Some(64 * 40 + 7));
+    assert_eq!(tree.first_free_from(64 * 40 + 8), Some(64 * 1050));
+    assert_eq!(tree.first_free_from(64 * 1050 + 1), None);
+    assert_eq!(tree.first_free_from(64 * 2000), None);
+  }
+
+  #[test]
+  fn range_queries() {
+    let mut tree = AcceleratedBitmap::new(2);
+    tree.resize(300);
+    for idx in 60 .. 200 { tree.set(idx, true) }
+
+    // Fully set, crossing word boundaries
+    assert!(tree.all_set(60 .. 200));
+    assert!(tree.all_set(63 .. 129));
+    assert!(tree.any_set(63 .. 129));
+    // Fully clear
+    assert!(!tree.any_set(0 .. 60));
+    assert!(!tree.any_set(200 .. 300));
+    assert!(!tree.all_set(200 .. 201));
+    // Mixed at either edge
+    assert!(!tree.all_set(59 .. 200));
+    assert!(!tree.all_set(60 .. 201));
+    assert!(tree.any_set(0 .. 61));
+    assert!(tree.any_set(199 .. 300));
+    // Empty ranges, and ranges past the end
+    assert!(tree.all_set(100 .. 100));
+    assert!(!tree.any_set(100 .. 100));
+    assert!(!tree.all_set(150 .. 1000));
+    assert!(!tree.any_set(300 .. 1000));
+  }
+
+  #[test]
+  fn last_set() {
+    let mut tree = AcceleratedBitmap::new(2);
+    tree.resize(300);
+    assert_eq!(tree.last_set(), None);
+    tree.set(0, true);
+    assert_eq!(tree.last_set(), Some(0));
+    tree.set(191, true);
+    assert_eq!(tree.last_set(), Some(191));
+    tree.set(192, true);
+    assert_eq!(tree.last_set(), Some(192));
+
+    // Deep enough to walk the layers down
+    let mut tree = AcceleratedBitmap::new(3);
+    tree.resize(64 * 3000);
+    for idx in [5, 64 * 33 + 1, 64 * 1024 + 9, 64 * 2999 + 63] {
+      tree.set(idx, true);
+      assert_eq!(tree.last_set(), Some(idx));
+    }
+    tree.set(64 * 2999 + 63, false);
+    assert_eq!(tree.last_set(), Some(64 * 1024 + 9));
+    tree.set(64 * 1024 + 9, false);
+    assert_eq!(tree.last_set(), Some(64 * 33 + 1));
+  }
+
+  #[test]
+  fn rebuild_from_base() {
+    let mut set = AcceleratedBitmap::new(3);
+    set.resize(64 * 2000);
+    for idx in 0 .. 64 * 2000 {
+      if idx % 7 != 0 || idx < 64 * 100 { set.set(idx, true) }
+    }
+
+    let mut rebuilt = AcceleratedBitmap::new(3);
+    rebuilt.resize(10);
+    rebuilt.set(3, true);
+    rebuilt.rebuild_from_base(set.base.clone());
+    assert_eq!(rebuilt.accel_layers, set.accel_layers);
+    assert_eq!(rebuilt.first_free(), set.first_free());
+    assert_eq!(rebuilt.validate(64 * 2000), Ok(()));
+  }
+
+  #[test]
+  fn set_searches() {
+    let mut tree = AcceleratedBitmap::new(3);
+    tree.resize(64 * 3000);
+    let set = [3, 64, 65, 64 * 40 + 7, 64 * 2999 + 63];
+    for idx in set { tree.set(idx, true) }
+    for start in (0 .. 64 * 3000).step_by(13) {
+      assert_eq!(tree.first_set_from(start), set.iter().copied().find(|idx| *idx >= start));
+      assert_eq!(tree.last_set_before(start), set.iter().copied().rfind(|idx| *idx < start));
+    }
+    assert_eq!(tree.first_set_from(64 * 3000), None);
+    assert_eq!(tree.last_set(), Some(64 * 2999 + 63));
+    // Has to climb to the top layer and back down across the gap, and stops short of bits at end
+    assert_eq!(tree.last_set_before(64 * 2999 + 63), Some(64 * 40 + 7));
+    assert_eq!(tree.last_set_before(usize::MAX), Some(64 * 2999 + 63));
+    assert_eq!(tree.last_set_before(3), None);
+
+    // Without layers it falls back to scanning the base
+    let mut flat = AcceleratedBitmap::new(0);
+    flat.resize(64 * 10);
+    for idx in [5, 64 * 3 + 1] { flat.set(idx, true) }
+    assert_eq!(flat.last_set_before(64 * 9), Some(64 * 3 + 1));
+    assert_eq!(flat.last_set_before(64 * 3 + 1), Some(5));
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn compact_serde() {
+    let mut tree = AcceleratedBitmap::new(3);
+    tree.resize(64 * 1500);
+    for idx in (0 .. 64 * 1500).filter(|idx| idx % 5 != 0 || *idx < 64 * 40) { tree.set(idx, true) }
+
+    let json = serde_json::to_string(&tree).unwrap();
+    assert!(!json.contains("accel_layers"));
+    let restored: AcceleratedBitmap = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.base, tree.base);
+    assert_eq!(restored.accel_layers, tree.accel_layers);
+    assert_eq!(restored.first_free(), tree.first_free());
+    assert_eq!(restored.first_free_from(64 * 40), tree.first_free_from(64 * 40));
+  }
+
+  #[test]
+  fn replace() {
+    let mut tree = AcceleratedBitmap::new(3);
+    tree.resize(64 * 40);
+    for idx in 0 .. 64 * 40 { assert!(!tree.replace(idx, true)) }
+    assert!(tree.replace(64 * 7, false));
+    assert!(!tree.replace(64 * 7, false));
+    assert!(tree.replace(5, true));
+    assert_eq!(tree.first_free(), Some(64 * 7));
+    assert_eq!(tree.validate(64 * 40), Ok(()));
+  }
+
+  #[test]
+  fn validate() {
+    let mut tree = AcceleratedBitmap::new(3);
+    tree.resize(5000);
+    for idx in (0 .. 5000).step_by(3) { tree.set(idx, true) }
+    assert_eq!(tree.validate(5000), Ok(()));
+    assert!(tree.validate(4000).is_err());
+
+    // Desync the base from the layers summarizing it
+    tree.base[10] = u64::MAX;
+    assert!(tree.validate(5000).is_err());
+    tree.base[10] = 0;
+    // Setting any bit in the word re-summarizes it
+    tree.set(64 * 10, false);
+    assert_eq!(tree.validate(5000), Ok(()));
+
+    // Corrupt a summary directly
+    tree.accel_layers[1][0] ^= 1 << 32;
+    assert!(tree.validate(5000).is_err());
+  }
+
+  #[test]
+  fn lazy_layers() {
+    let mut tree = AcceleratedBitmap::new(3);
+    // A single word doesn't need any acceleration
+    tree.resize(64);
+    assert_eq!(tree.accel_layers.len(), 0);
+    // Spanning multiple words does, but only as much as it takes to reach one word
+    tree.resize(64 * 33);
+    assert_eq!(tree.accel_layers.len(), 2);
+    assert_eq!(tree.accel_layers[1].len(), 1);
+    // Layers never exceed the requested maximum
+    tree.resize(64 * 32 * 32 * 32 * 2);
+    assert_eq!(tree.accel_layers.len(), 3);
+    tree.resize(10);
+    assert_eq!(tree.accel_layers.len(), 0);
+  }
+
+  #[test]
+  fn shrink_after_growth() {
+    let mut tree = AcceleratedBitmap::new(3);
+    tree.resize(5_000_000);
+    for idx in (0 .. 5_000_000).step_by(1000) { tree.set(idx, true) }
+    let grown = tree.memory_usage();
+    assert!(grown > 5_000_000 / 8);
+
+    tree.resize(10);
+    // The layers a handful of slots don't need are gone immediately, the base keeps its capacity
+    assert_eq!(tree.accel_layers.len(), 0);
+    tree.shrink_to_fit();
+    assert!(tree.memory_usage() <= 8);
+    assert!(tree.is_set(0));
+  }
+
+  #[test]
+  fn first_free_across_words() {
+    let mut tree = AcceleratedBitmap::new(2);
+    tree.resize(64 * 40);
+    for idx in 0 .. 64 * 40 { tree.set(idx, true) }
+    // Completely full means nothing is free
+    assert_eq!(tree.first_free(), None);
+    tree.set(64 * 35 + 3, false);
+    assert_eq!(tree.first_free(), Some(64 * 35 + 3));
+    // Growing keeps existing bits and exposes the new space after them
+    tree.set(64 * 35 + 3, true);
+    tree.resize(64 * 40 + 5);
+    assert_eq!(tree.first_free(), Some(64 * 40));
   }
 
 }
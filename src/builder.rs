@@ -0,0 +1,90 @@
+use crate::Pond;
+use std::marker::PhantomData;
+
+/// Configures a [Pond] one option at a time, for when more than one of its constructors would apply, see [Pond::builder].
+///
+/// ```
+/// use lilypads::Pond;
+///
+/// let mut cache = Pond::builder().layers(1).strict().max_capacity(2).build();
+/// let _ = cache.insert('a');
+/// let _ = cache.insert('b');
+/// let (_, evicted) = cache.insert_evicting('c');
+/// assert_eq!(evicted, Some('a'));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PondBuilder<T, M: Default = ()> {
+  pub(crate) layers: usize,
+  pub(crate) capacity: usize,
+  pub(crate) zeroing: bool,
+  pub(crate) strict: bool,
+  pub(crate) recency: bool,
+  pub(crate) refcounting: bool,
+  pub(crate) eviction_cap: Option<usize>,
+  pub(crate) free_stack: bool,
+  _pond: PhantomData<fn() -> Pond<T, M>>,
+}
+impl<T, M: Default> PondBuilder<T, M> {
+  pub(crate) fn new() -> Self {
+    Self {
+      layers: 3,
+      capacity: 0,
+      zeroing: false,
+      strict: false,
+      recency: false,
+      refcounting: false,
+      eviction_cap: None,
+      free_stack: false,
+      _pond: PhantomData,
+    }
+  }
+
+  /// Caps the bitmap's acceleration layers, see [Pond::with_layers].
+  pub fn layers(mut self, layers: usize) -> Self { self.layers = layers; self }
+
+  /// Allocates room for `capacity` values up front, so the first inserts don't reallocate.
+  pub fn capacity(mut self, capacity: usize) -> Self { self.capacity = capacity; self }
+
+  /// Scrubs slots as they're freed, see [Pond::new_zeroing].
+  pub fn zeroing(mut self) -> Self { self.zeroing = true; self }
+
+  /// Treats overwrites and out of bounds frees as bugs in debug builds, see [Pond::new_strict].
+  pub fn strict(mut self) -> Self { self.strict = true; self }
+
+  /// Remembers the order slots were written in, see [Pond::with_recency].
+  pub fn recency(mut self) -> Self { self.recency = true; self }
+
+  /// Counts handles to each value, see [Pond::with_refcounting].
+  pub fn refcounting(mut self) -> Self { self.refcounting = true; self }
+
+  /// Evicts the oldest value once the first `max_capacity` slots are full, see [Pond::with_eviction].
+  ///
+  /// # Panics
+  /// If `max_capacity` is 0.
+  pub fn max_capacity(mut self, max_capacity: usize) -> Self {
+    assert!(max_capacity > 0, "a pond with an eviction cap of 0 can't hold anything");
+    self.eviction_cap = Some(max_capacity);
+    self
+  }
+
+  /// Reuses the most recently freed slot on insert, see [Pond::with_free_stack].
+  pub fn free_stack(mut self) -> Self { self.free_stack = true; self }
+
+  /// Switches the metadata column to `N`, see [Pond::with_metadata].
+  pub fn metadata<N: Default>(self) -> PondBuilder<T, N> {
+    PondBuilder {
+      layers: self.layers,
+      capacity: self.capacity,
+      zeroing: self.zeroing,
+      strict: self.strict,
+      recency: self.recency,
+      refcounting: self.refcounting,
+      eviction_cap: self.eviction_cap,
+      free_stack: self.free_stack,
+      _pond: PhantomData,
+    }
+  }
+
+  /// Creates the configured [Pond].
+  pub fn build(self) -> Pond<T, M> { Pond::from_builder(self) }
+}
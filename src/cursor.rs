@@ -0,0 +1,40 @@
+use crate::Pond;
+
+/// Walks the occupied slots of a pond in ascending order while allowing edits along the way, see [Pond::cursor].
+///
+/// Unlike [Pond::iter_mut] the cursor only borrows one value at a time,
+/// so the current value can be freed or new ones inserted mid-traversal.
+pub struct Cursor<'a, T, M: Default = ()> {
+  pond: &'a mut Pond<T, M>,
+  current: Option<usize>,
+  // The first index which hasn't been visited yet
+  next: usize,
+}
+impl<'a, T, M: Default> Cursor<'a, T, M> {
+  pub(crate) fn new(pond: &'a mut Pond<T, M>) -> Self { Self { pond, current: None, next: 0 } }
+
+  /// Moves to the next occupied slot, returning its index and value. Returns None once the end is reached.
+  #[allow(clippy::should_implement_trait)]
+  pub fn next(&mut self) -> Option<(usize, &mut T)> {
+    let idx = self.pond.next_occupied(self.next)?;
+    self.current = Some(idx);
+    self.next = idx + 1;
+    Some( (idx, self.pond.get_mut(idx)?) )
+  }
+
+  /// Frees the slot the cursor is sitting on, returning its value.
+  /// Returns None if the cursor hasn't moved yet or the current value was already removed.
+  pub fn remove_current(&mut self) -> Option<T> { self.pond.free(self.current.take()?) }
+
+  /// Stores `value` in the first free slot after the cursor, growing the pond if there isn't one,
+  /// and returns its index. The new value will be visited by a later [Cursor::next].
+  ///
+  /// If that slot is past the cap of a pond made with [Pond::with_eviction], this [Pond::insert]s instead,
+  /// so the value may land (and be evicted) behind the cursor where it won't be visited.
+  pub fn insert_after(&mut self, value: T) -> usize {
+    let idx = self.pond.next_free(self.next);
+    if !self.pond.under_cap(idx) { return self.pond.insert(value) }
+    self.pond.write(idx, value);
+    idx
+  }
+}
@@ -0,0 +1,29 @@
+use std::fmt;
+
+/// Why a checked access like [crate::Pond::get_checked] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotError {
+  /// The index is past the end of the pond.
+  OutOfBounds {
+    /// The index which was requested
+    idx: usize,
+    /// The pond's [crate::Pond::len] at the time
+    len: usize,
+  },
+  /// The slot exists but doesn't hold a value.
+  Vacant {
+    /// The index which was requested
+    idx: usize,
+  },
+}
+
+impl fmt::Display for SlotError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::OutOfBounds { idx, len } => write!(f, "slot {idx} is out of bounds for a pond of length {len}"),
+      Self::Vacant { idx } => write!(f, "slot {idx} is vacant"),
+    }
+  }
+}
+
+impl std::error::Error for SlotError {}
@@ -0,0 +1,65 @@
+use crate::Pond;
+use crate::bitmap::SetBits;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ops::Range;
+
+/// Iterator over the occupied slots of a pond as (item_idx, &mut T), see [Pond::iter_mut](crate::Pond::iter_mut).
+///
+/// It's [Send] whenever `T` is, so it can be handed off to another thread.
+pub struct IterMut<'a, T> {
+  bits: SetBits<'a>,
+  data: *mut MaybeUninit<T>,
+  _values: PhantomData<&'a mut T>,
+}
+impl<'a, T> IterMut<'a, T> {
+  // Every set bit in `bits` must be an initialized slot of `data`, which has to be borrowed mutably for 'a
+  pub(crate) unsafe fn new(bits: SetBits<'a>, data: *mut MaybeUninit<T>) -> Self {
+    Self { bits, data, _values: PhantomData }
+  }
+}
+
+// It only ever hands out disjoint &mut T, so it's as thread safe as they are
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+  type Item = (usize, &'a mut T);
+  fn next(&mut self) -> Option<Self::Item> {
+    let idx = self.bits.next()?;
+    // Each occupied slot is visited once, so no two of the references handed out alias
+    Some( (idx, unsafe { (*self.data.add(idx)).assume_init_mut() }) )
+  }
+}
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    let idx = self.bits.next_back()?;
+    Some( (idx, unsafe { (*self.data.add(idx)).assume_init_mut() }) )
+  }
+}
+
+/// Frees and yields every occupied slot within a range as (item_idx, T), see [Pond::drain_range].
+///
+/// Dropping it early still frees whatever's left of the range.
+pub struct DrainRange<'a, T, M: Default = ()> {
+  pond: &'a mut Pond<T, M>,
+  next: usize,
+  end: usize,
+}
+impl<'a, T, M: Default> DrainRange<'a, T, M> {
+  pub(crate) fn new(pond: &'a mut Pond<T, M>, range: Range<usize>) -> Self {
+    let end = range.end.min(pond.len());
+    Self { pond, next: range.start, end }
+  }
+}
+impl<T, M: Default> Iterator for DrainRange<'_, T, M> {
+  type Item = (usize, T);
+  fn next(&mut self) -> Option<Self::Item> {
+    let idx = self.pond.next_occupied(self.next).filter(|idx| *idx < self.end)?;
+    self.next = idx + 1;
+    Some( (idx, self.pond.free(idx)?) )
+  }
+}
+impl<T, M: Default> Drop for DrainRange<'_, T, M> {
+  fn drop(&mut self) { self.for_each(drop) }
+}
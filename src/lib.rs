@@ -12,6 +12,10 @@
 //!
 //! This crate isn't yet thread safe, but that's eventually on the todo list probably.
 //!
+//! Serde support is behind the default `serde` feature, disable default features if you don't need it.
+//!
+//! The `simd` feature scans for free slots several words at a time on x86_64, which helps huge ponds built with few layers.
+//!
 //! # Example
 //! ```
 //! use lilypads::Pond;
@@ -49,9 +53,29 @@
 //! ```
 
 mod bitmap;
+mod builder;
+mod cursor;
+mod error;
+mod iter;
+mod occupancy;
 mod pondaos;
+#[cfg(feature = "serde")]
+mod size;
+mod snapshot;
 // mod pondsoa;
+mod sparseset;
+mod transaction;
 
+pub use builder::PondBuilder;
+pub use cursor::Cursor;
+pub use error::SlotError;
+pub use iter::{DrainRange, IterMut};
+pub use occupancy::OccupancySnapshot;
 pub use pondaos::Pond;
+#[cfg(feature = "serde")]
+pub use size::SizeError;
+pub use snapshot::Snapshot;
+pub use sparseset::SparseSet;
+pub use transaction::{SlotToken, Transaction};
 // pub use pondsoa::PondSoa;
 
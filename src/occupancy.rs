@@ -0,0 +1,23 @@
+use crate::bitmap::set_bits;
+
+/// A frozen copy of which slots in a [crate::Pond] were occupied, see [crate::Pond::occupancy_snapshot].
+///
+/// Snapshots are just the occupancy bits, so they're cheap to take and cheap to compare.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OccupancySnapshot {
+  words: Vec<u64>,
+}
+impl OccupancySnapshot {
+  pub(crate) fn new(words: &[u64]) -> Self { Self { words: words.to_vec() } }
+
+  /// Compares this snapshot against a later one, returning (added, removed) indices in ascending order.
+  ///
+  /// Added slots are occupied in `other` but not `self`, removed slots are the opposite.
+  pub fn diff(&self, other: &OccupancySnapshot) -> (Vec<usize>, Vec<usize>) {
+    let len = self.words.len().max(other.words.len());
+    let word = |words: &[u64], idx: usize| words.get(idx).copied().unwrap_or(0);
+    let added = set_bits( (0 .. len).map(|idx| !word(&self.words, idx) & word(&other.words, idx)) );
+    let removed = set_bits( (0 .. len).map(|idx| word(&self.words, idx) & !word(&other.words, idx)) );
+    (added.collect(), removed.collect())
+  }
+}
@@ -1,64 +1,551 @@
 #![warn(missing_docs)]
-use crate::bitmap::AcceleratedBitmap;
-use std::collections::HashMap;
+use crate::bitmap::{AcceleratedBitmap, set_bits};
+use crate::{Cursor, DrainRange, IterMut, OccupancySnapshot, PondBuilder, SlotError, SlotToken, Snapshot, Transaction};
+use std::collections::{HashMap, HashSet, TryReserveError};
+use std::hash::Hash;
 use std::mem::MaybeUninit;
+use std::ops::Range;
+
+// The fewest slots reserve will grow the allocation by
+const MIN_GROWTH: usize = 8;
 
 /// The struct used to pool T.
 ///
 /// The first available node will be assigned when you call [Pond::insert],
 /// intending to keep the data as contiguous as possible. If you need total contiguity,
 /// [Pond::defrag] and [Pond::trim] should help with that.
+///
+/// Zero-sized types never allocate any data, only the occupancy bitmap,
+/// so a `Pond<()>` makes for a cheap set of indices.
+///
+/// `M` is an optional per-slot metadata column (see [Pond::with_metadata]), which costs nothing when left as `()`.
+///
+/// Unsized values like trait objects can be pooled behind a box, with [Pond::get_dyn] to skip the double deref:
+/// ```
+/// use lilypads::Pond;
+/// use std::fmt::Display;
+///
+/// let mut pool: Pond<Box<dyn Display>> = Pond::new();
+/// let idx = pool.insert(Box::new(5));
+/// let _ = pool.insert(Box::new("five"));
+/// assert_eq!(pool.get_dyn(idx).unwrap().to_string(), "5");
+/// ```
 #[derive(Debug)]
-pub struct Pond<T> {
+pub struct Pond<T, M: Default = ()> {
   data : Vec< MaybeUninit<T> >,
   bitmap: AcceleratedBitmap,
+  // Side data for each slot, reset to default whenever a slot is reserved
+  meta: Vec<M>,
+  // When each slot was last written, only tracked if requested
+  recency: Option< Vec<u64> >,
+  sequence: u64,
+  // How many handles each slot has out, only tracked if requested
+  refcounts: Option< Vec<u32> >,
+  // Whether freed slots get scrubbed
+  zeroing: bool,
+  // Whether writing over an occupied slot is treated as a bug, only checked in debug builds
+  strict: bool,
+  // How many slots insert fills before it starts evicting the oldest, if it ever does
+  eviction_cap: Option<usize>,
+  // Recently freed slots for insert to pop, only kept if requested. Entries can go stale, so they're checked when popped
+  free_stack: Option< Vec<usize> >,
+  // Every slot before this one is occupied, so the search for a free slot can start here
+  free_hint: usize,
+  // How many slots are occupied now, and the most that have been at once
+  occupied: usize,
+  high_water: usize,
 }
-impl<T> Pond<T> {
+impl<T, M: Default> Pond<T, M> {
+
+  fn empty() -> Self {
+    Self {
+      data : Vec::new(),
+      bitmap: AcceleratedBitmap::new(3),
+      meta: Vec::new(),
+      recency: None,
+      sequence: 0,
+      refcounts: None,
+      zeroing: false,
+      strict: false,
+      eviction_cap: None,
+      free_stack: None,
+      free_hint: 0,
+      occupied: 0,
+      high_water: 0,
+    }
+  }
 
+  pub(crate) fn from_builder(builder: PondBuilder<T, M>) -> Self {
+    let mut pond = Self {
+      bitmap: AcceleratedBitmap::new(builder.layers),
+      recency: (builder.recency || builder.eviction_cap.is_some()).then(Vec::new),
+      refcounts: builder.refcounting.then(Vec::new),
+      zeroing: builder.zeroing,
+      strict: builder.strict,
+      eviction_cap: builder.eviction_cap,
+      free_stack: builder.free_stack.then(Vec::new),
+      ..Self::empty()
+    };
+    pond.data.reserve_exact(builder.capacity);
+    pond
+  }
+
+  /// THIS FUNCTION DOESN'T BOUND CHECK
+  pub(crate) fn mark_free(&mut self, idx:usize) { 
+    self.bitmap.set(idx, false);
+    self.release(idx);
+  }
+
+  /// Everything [Pond::mark_free] does besides clearing the bit, for callers which clear whole words at once.
   /// THIS FUNCTION DOESN'T BOUND CHECK
-  fn mark_free(&mut self, idx:usize) { self.bitmap.set(idx, false) }
+  fn release(&mut self, idx: usize) {
+    self.occupied -= 1;
+    self.free_hint = self.free_hint.min(idx);
+    if self.zeroing { self.zero_slot(idx) }
+    if let Some(refcounts) = &mut self.refcounts { refcounts[idx] = 0 }
+    if let Some(stack) = &mut self.free_stack {
+      // Stale entries could otherwise pile up forever, and forgetting free slots is fine since reserve falls back on the bitmap
+      if stack.len() >= self.data.len() { stack.clear() }
+      stack.push(idx);
+    }
+  }
 
+  /// Overwrites the slot's bytes with zeros, in a way the compiler won't optimize out.
   /// THIS FUNCTION DOESN'T BOUND CHECK
-  fn mark_reserved(&mut self, idx:usize) { self.bitmap.set(idx, true); }
+  fn zero_slot(&mut self, idx: usize) {
+    unsafe { std::ptr::write_volatile(&mut self.data[idx], MaybeUninit::zeroed()) }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+  }
+
+  /// THIS FUNCTION DOESN'T BOUND CHECK
+  fn mark_reserved(&mut self, idx:usize) { 
+    if !self.bitmap.replace(idx, true) {
+      self.occupied += 1;
+      self.high_water = self.high_water.max(self.occupied);
+      // Only fresh slots start with one handle, overwriting a value leaves its handles pointing at the new one
+      if let Some(refcounts) = &mut self.refcounts { refcounts[idx] = 1 }
+    }
+    self.meta[idx] = M::default();
+    if let Some(recency) = &mut self.recency {
+      recency[idx] = self.sequence;
+      self.sequence += 1;
+    }
+  }
+
+  /// Marks every slot set in `words` (one per base word) as reserved in one go, rather than walking the bitmap per slot.
+  /// Everything should be free beforehand, and the values must already be written.
+  fn mark_reserved_words(&mut self, words: Vec<u64>) {
+    for idx in set_bits(words.iter().copied()) {
+      self.meta[idx] = M::default();
+      if let Some(recency) = &mut self.recency {
+        recency[idx] = self.sequence;
+        self.sequence += 1;
+      }
+      if let Some(refcounts) = &mut self.refcounts { refcounts[idx] = 1 }
+    }
+    self.bitmap.rebuild_from_base(words);
+    self.free_hint = 0;
+    self.occupied = self.bitmap.count_set();
+    self.high_water = self.high_water.max(self.occupied);
+  }
+
+  /// [Pond::mark_reserved_words] for slots 0 .. count.
+  fn mark_reserved_front(&mut self, count: usize) {
+    let mut words = vec![u64::MAX; count / 64];
+    if !count.is_multiple_of(64) { words.push(!(u64::MAX << (count % 64))) }
+    self.mark_reserved_words(words);
+  }
+
+  /// Grows the pond so `idx` is in bounds, panicking rather than wrapping if a pond that long can't be indexed.
+  fn grow_to_include(&mut self, idx: usize) {
+    let size = idx.checked_add(1).unwrap_or_else(|| panic!("slot {idx} is past the largest index a pond can hold"));
+    self.resize(size)
+  }
 
   #[must_use]
-  fn reserve(&mut self) -> usize {
-    let idx = self.bitmap.first_free().unwrap_or(self.len());
-    if idx >= self.len() { self.resize(idx + 1) }
+  pub(crate) fn reserve(&mut self) -> usize { self.reserve_evicting().0 }
+
+  // Claims the slot the next insert goes in, evicting the oldest value first if the pond is at its eviction cap.
+  // Every insert which doesn't need a particular slot comes through here.
+  fn reserve_evicting(&mut self) -> (usize, Option<T>) {
+    let evicted = self.evict_oldest();
+    let idx = match self.pop_free_stack() {
+      Some(idx) => idx,
+      None => {
+        // Runs of inserts pick up where the last one left off, rather than descending from the top layer each time
+        let idx = self.bitmap.first_free_from(self.free_hint).unwrap_or(self.len());
+        self.free_hint = idx + 1;
+        idx
+      }
+    };
+    if idx >= self.len() {
+      // Double the allocation rather than leaving growth up to Vec, so runs of inserts reallocate O(log n) times
+      if idx >= self.data.capacity() { self.data.reserve_exact(self.len().max(MIN_GROWTH)) }
+      self.grow_to_include(idx)
+    }
     self.mark_reserved(idx);
-    idx
+    (idx, evicted)
+  }
+
+  // Whether a value can go in slot `idx` without passing the eviction cap
+  pub(crate) fn under_cap(&self, idx: usize) -> bool { self.eviction_cap.is_none_or(|cap| idx < cap) }
+
+  // For inserts which need one particular slot, and so can't evict their way into another
+  fn assert_under_cap(&self, idx: usize, caller: &str) {
+    assert!(self.under_cap(idx), "{caller} would put a value in slot {idx}, past the pond's eviction cap");
+  }
+
+  // The most recently freed slot which is still free (and under the eviction cap), if the pond keeps a free stack
+  fn pop_free_stack(&mut self) -> Option<usize> {
+    let end = self.data.len().min(self.eviction_cap.unwrap_or(usize::MAX));
+    let stack = self.free_stack.as_mut()?;
+    while let Some(idx) = stack.pop() {
+      if idx < end && !self.bitmap.is_set(idx) { return Some(idx) }
+    }
+    None
+  }
+
+  /// Swaps slots `a` and `b`, along with everything tracked about them. Either may be free.
+  /// THIS FUNCTION DOESN'T BOUND CHECK
+  fn swap_slots(&mut self, a: usize, b: usize) {
+    self.data.swap(a, b);
+    let (a_set, b_set) = (self.bitmap.is_set(a), self.bitmap.is_set(b));
+    if a_set != b_set {
+      self.bitmap.set(a, b_set);
+      self.bitmap.set(b, a_set);
+      self.free_hint = self.free_hint.min(a.min(b));
+    }
+    self.meta.swap(a, b);
+    if let Some(recency) = &mut self.recency { recency.swap(a, b) }
+    if let Some(refcounts) = &mut self.refcounts { refcounts.swap(a, b) }
   }
 
 }
 impl<T> Pond<T> {
   /// Creates a new instance of [Pond]
-  pub fn new() -> Self {
-    Self {
-      data : Vec::new(),
-      bitmap: AcceleratedBitmap::new(3),
+  pub fn new() -> Self { Self::empty() }
+
+  /// Starts a [PondBuilder], for combining the options the other constructors each turn on.
+  pub fn builder() -> PondBuilder<T> { PondBuilder::new() }
+
+  /// Creates a new instance of [Pond] whose occupancy bitmap stacks at most `layers` acceleration layers,
+  /// rather than the default of 3.
+  ///
+  /// Each layer lets searches for free slots skip 32 times as many slots, and layers are only built once the pond
+  /// is big enough to need them. With `n` layers a search scans `len / (64 * 32^n)` words along the top, so 3 covers
+  /// a couple million slots in a single word. Use 0 or 1 for ponds of a few thousand slots, and 4 or more past a
+  /// few hundred million.
+  pub fn with_layers(layers: usize) -> Self {
+    Self { bitmap: AcceleratedBitmap::new(layers), ..Self::new() }
+  }
+
+  /// Creates a new instance of [Pond] which carries an `M` alongside each value, see [Pond::insert_tagged].
+  /// Metadata moves with its value through [Pond::defrag], and is reset to `M::default()` whenever a slot is reserved.
+  pub fn with_metadata<M: Default>() -> Pond<T, M> { Pond::empty() }
+
+  /// Builds a pond with one slot per entry of `values`, occupying the slots holding Some.
+  /// The inverse of [Pond::into_option_vec].
+  pub fn from_option_vec(values: Vec<Option<T>>) -> Self {
+    let mut pool = Self::new();
+    pool.resize(values.len());
+    for (idx, pot_val) in values.into_iter().enumerate() {
+      if let Some(val) = pot_val { pool.write(idx, val); }
     }
+    pool
+  }
+
+  /// Creates an empty [Pond] as long as `other`, for keeping a second column of data index-aligned with it.
+  pub fn new_aligned_to<U, N: Default>(other: &Pond<U, N>) -> Self {
+    let mut pool = Self::new();
+    pool.resize(other.len());
+    pool
+  }
+
+  /// Creates a new instance of [Pond] which overwrites slots with zeros once they're freed,
+  /// so values like keys don't linger in memory waiting to be reused.
+  ///
+  /// This isn't a complete defense: copies made when values are moved in or out of the pond,
+  /// and old allocations left behind when the pond grows, aren't scrubbed.
+  pub fn new_zeroing() -> Self {
+    Self { zeroing: true, ..Self::new() }
+  }
+
+  /// Creates a new instance of [Pond] which panics when [Pond::write] lands on an occupied slot, since two things
+  /// claiming the same index is usually a bug. Only checked in debug builds, release builds overwrite as normal.
+  /// [Pond::set] is still allowed to overwrite.
+  ///
+  /// [Pond::free]ing an index past [Pond::len] panics too, freeing a free slot inside the pond is fine.
+  pub fn new_strict() -> Self {
+    Self { strict: true, ..Self::new() }
+  }
+
+  /// Creates a new instance of [Pond] which remembers the order slots were written in, see [Pond::iter_by_recency].
+  /// This costs an extra u64 per slot.
+  pub fn with_recency() -> Self {
+    Self { recency: Some(Vec::new()), ..Self::new() }
+  }
+
+  /// Creates a new instance of [Pond] which counts handles to each value, for shared ownership without `Rc`.
+  /// Each insert hands out one handle, [Pond::clone_handle] adds more and [Pond::free_handle] gives them back.
+  ///
+  /// Costs an extra u32 per slot. [Pond::free] still frees a value outright, whatever its count.
+  pub fn with_refcounting() -> Self {
+    Self { refcounts: Some(Vec::new()), ..Self::new() }
+  }
+
+  /// Creates a new instance of [Pond] which acts as a bounded cache, see [Pond::insert_evicting].
+  /// Once the first `max_capacity` slots are all occupied, each insert evicts the oldest value to make room.
+  ///
+  /// Every insert respects the cap. Ones which need a particular slot ([Pond::get_or_insert_with],
+  /// [Pond::reserve_block_default]...) panic rather than go past it, but [Pond::write] and [Pond::resize] can still
+  /// place values beyond it. Evicted values are dropped for good, rolling back a [Transaction] won't bring them back.
+  /// This tracks recency like [Pond::with_recency].
+  ///
+  /// # Panics
+  /// If `max_capacity` is 0, since there'd be nowhere to insert anything.
+  pub fn with_eviction(max_capacity: usize) -> Self {
+    assert!(max_capacity > 0, "a pond with an eviction cap of 0 can't hold anything");
+    Self { eviction_cap: Some(max_capacity), ..Self::with_recency() }
   }
+
+  /// Creates a new instance of [Pond] which remembers freed slots on a stack, so [Pond::insert] reuses the most
+  /// recently freed slot in O(1) rather than searching the bitmap for the first free one.
+  ///
+  /// Good for heavy insert/free churn, at the cost of values drifting away from the front of the pond.
+  /// Inserts fall back to the usual search once the stack runs dry, and [Pond::defrag] and [Pond::trim] work as normal.
+  pub fn with_free_stack() -> Self {
+    Self { free_stack: Some(Vec::new()), ..Self::new() }
+  }
+}
+impl<T, M: Default> Pond<T, M> {
   
   /// Checks whether the provided index has an associated value
   pub fn is_occupied(&self, idx: usize) -> bool {
     if idx < self.data.len() { self.bitmap.is_set(idx) } else { false }
   }
 
+  /// Checks whether every slot in `range` is occupied. Slots beyond [Pond::len] are never occupied.
+  pub fn is_range_occupied(&self, range: Range<usize>) -> bool { self.bitmap.all_set(range) }
+
+  /// Checks whether every slot in `range` is free.
+  pub fn is_range_free(&self, range: Range<usize>) -> bool { !self.bitmap.any_set(range) }
+
   /// Returns the number of slots held internally, both free and full.
   pub fn len(&self) -> usize { self.data.len() }
 
+  /// Returns the number of slots the pond can hold without reallocating.
+  pub fn capacity(&self) -> usize { self.data.capacity() }
+
+  /// Returns the highest occupied index, or None if nothing is occupied.
+  pub fn last_occupied(&self) -> Option<usize> { self.bitmap.last_set() }
+
+  /// Returns the occupied index closest to `idx` (`idx` itself if it's occupied), or None if nothing is occupied.
+  /// Ties between an index below and above `idx` go to the lower one.
+  pub fn nearest_occupied(&self, idx: usize) -> Option<usize> {
+    let above = self.bitmap.first_set_from(idx);
+    let below = self.bitmap.last_set_before(idx);
+    match (below, above) {
+      (Some(below), Some(above)) => Some( if idx - below <= above - idx { below } else { above } ),
+      (below, above) => below.or(above),
+    }
+  }
+
+  /// Returns the number of occupied slots.
+  pub fn count(&self) -> usize { self.occupied }
+
+  /// Returns the most slots that have been occupied at once, since creation or the last [Pond::reset_high_water_mark].
+  /// Handy for working out how much to reserve up front.
+  pub fn high_water_mark(&self) -> usize { self.high_water }
+
+  /// Restarts [Pond::high_water_mark] from the current [Pond::count].
+  pub fn reset_high_water_mark(&mut self) { self.high_water = self.occupied }
+
+  /// Returns the number of occupied slots within `range`, which may run past [Pond::len] (`0 .. usize::MAX` counts everything).
+  pub fn count_in_range(&self, range: Range<usize>) -> usize { self.bitmap.count_set_in(range) }
+
+  /// Returns true if the pond holds no slots at all.
+  pub fn is_empty(&self) -> bool { self.len() == 0 }
+
   /// Returns the next index which will be assigned on a [Pond::insert] call. If you need to
   /// guarantee a specific index, use [Pond::write] instead.
-  pub fn next_index(&self) -> usize { self.bitmap.first_free().unwrap_or(self.len()) }
+  ///
+  /// Ponds made with [Pond::with_free_stack] hand out the most recently freed slot, not the first free one.
+  pub fn next_index(&self) -> usize {
+    self.stacked_free().next().unwrap_or_else(|| self.bitmap.first_free().unwrap_or(self.len()))
+  }
+
+  /// Returns the indices the next `n` [Pond::insert] calls will be assigned, in order, without reserving them.
+  pub fn peek_free(&self, n: usize) -> Vec<usize> {
+    // Each stacked slot is only handed out once, it's occupied from then on
+    let mut stacked = HashSet::new();
+    let mut peeked: Vec<usize> = self.stacked_free().filter(|idx| stacked.insert(*idx)).take(n).collect();
+    let mut next = 0;
+    while peeked.len() < n {
+      let idx = self.next_free(next);
+      next = idx + 1;
+      if !stacked.contains(&idx) { peeked.push(idx) }
+    }
+    peeked
+  }
+
+  // The free stack's entries which still point at free slots, in the order [Pond::pop_free_stack] would return them
+  fn stacked_free(&self) -> impl Iterator<Item = usize> + '_ {
+    let stack = self.free_stack.as_deref().unwrap_or_default();
+    let end = self.len().min(self.eviction_cap.unwrap_or(usize::MAX));
+    stack.iter().rev().copied().filter(move |idx| *idx < end && !self.bitmap.is_set(*idx))
+  }
+
+  // The first occupied slot at or after `start`
+  pub(crate) fn next_occupied(&self, start: usize) -> Option<usize> {
+    self.bitmap.first_set_from(start).filter(|idx| *idx < self.len())
+  }
+
+  // The first free slot at or after `start`, which may be at or beyond len
+  pub(crate) fn next_free(&self, start: usize) -> usize {
+    self.bitmap.first_free_from(start).filter(|idx| *idx < self.len()).unwrap_or(start.max(self.len()))
+  }
+
+  // The start of the first run of `n` free slots, which may run past len
+  fn free_run(&self, n: usize) -> usize {
+    let mut start = self.next_free(0);
+    while let Some(occupied) = self.next_occupied(start).filter(|idx| *idx < start.saturating_add(n)) {
+      start = self.next_free(occupied + 1);
+    }
+    start
+  }
 
   /// Sets Pond to hold `size` elements. If size < self.len(), excess data will be truncated and dropped.
   pub fn resize(&mut self, size: usize) {
     for idx in size .. self.len() {
-      if self.bitmap.is_set(idx) { unsafe { self.data[idx].assume_init_drop(); } }
+      if self.bitmap.is_set(idx) { 
+        unsafe { self.data[idx].assume_init_drop(); } 
+        if self.zeroing { self.zero_slot(idx) }
+        self.occupied -= 1;
+      }
     }
     self.data.reserve(size.saturating_sub(self.len()));
     unsafe { self.data.set_len(size); }
     self.bitmap.resize(size);
+    self.free_hint = self.free_hint.min(size);
+    self.meta.resize_with(size, M::default);
+    if let Some(recency) = &mut self.recency { recency.resize(size, 0) }
+    if let Some(refcounts) = &mut self.refcounts { refcounts.resize(size, 0) }
+    if let Some(stack) = &mut self.free_stack { stack.retain(|idx| *idx < size) }
+  }
+
+  /// [Pond::resize]s, unless that would drop an occupied slot, in which case the highest occupied index is returned
+  /// and nothing changes.
+  pub fn try_resize(&mut self, size: usize) -> Result<(), usize> {
+    if let Some(last) = self.last_occupied().filter(|last| *last >= size) { return Err(last) }
+    self.resize(size);
+    Ok(())
+  }
+
+  /// Grows the pond to hold `size` slots, returning true if that forced the backing storage to reallocate.
+  /// Does nothing if the pond is already at least `size` long.
+  pub fn try_grow(&mut self, size: usize) -> bool {
+    if size <= self.len() { return false }
+    let capacity = self.data.capacity();
+    self.resize(size);
+    self.data.capacity() != capacity
+  }
+
+  /// Makes sure the pond is at least `count` slots long, so an empty pond can take `count` inserts without reallocating.
+  /// Only a lower bound: slots which are already occupied still take up room.
+  pub fn reserve_for_occupancy(&mut self, count: usize) { self.try_grow(count); }
+
+  /// Makes sure at least `k` slots are free, growing the pond if it has to, and returns whether that worked.
+  /// Check this before a batch of inserts which mustn't fail partway through.
+  ///
+  /// For ponds made with [Pond::with_eviction] only slots under the cap count and the pond won't grow past it,
+  /// so true means the next `k` inserts won't evict anything. Other ponds can always make room.
+  pub fn ensure_free(&mut self, k: usize) -> bool {
+    let limit = self.eviction_cap.unwrap_or(usize::MAX);
+    let existing = self.len().min(limit);
+    let free = existing - self.count_in_range(0 .. existing);
+    if free >= k { return true }
+    if limit - existing < k - free { return false }
+    // Only reachable when the pond is shorter than the limit, so it's still at most the limit afterwards
+    self.resize(existing + k - free);
+    true
+  }
+
+  /// Reserves room for at least `additional` more slots without changing [Pond::len],
+  /// returning an error instead of aborting if the allocation fails.
+  pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+    self.data.try_reserve(additional)?;
+    self.meta.try_reserve(additional)?;
+    if let Some(recency) = &mut self.recency { recency.try_reserve(additional)? }
+    if let Some(refcounts) = &mut self.refcounts { refcounts.try_reserve(additional)? }
+    // Can't overflow, data would have already complained
+    self.bitmap.try_reserve(self.len() + additional)
+  }
+
+  /// Shrinks the backing storage as close to [Pond::len] as the allocator allows, including the bitmap's
+  /// acceleration layers. Pair with [Pond::trim] to release as much memory as possible.
+  pub fn shrink_to_fit(&mut self) {
+    self.data.shrink_to_fit();
+    self.meta.shrink_to_fit();
+    if let Some(recency) = &mut self.recency { recency.shrink_to_fit() }
+    if let Some(refcounts) = &mut self.refcounts { refcounts.shrink_to_fit() }
+    if let Some(stack) = &mut self.free_stack { stack.shrink_to_fit() }
+    self.bitmap.shrink_to_fit();
+  }
+
+  /// Shrinks the pond towards `min_capacity`, mirroring [Vec::shrink_to]. Free slots at the end are truncated
+  /// down to `min_capacity`, but never past the last occupied slot, then spare capacity is released.
+  ///
+  /// Nothing is moved, [Pond::defrag] first if you want the occupied slots packed towards the front.
+  pub fn shrink_to(&mut self, min_capacity: usize) {
+    let keep = self.last_occupied().map_or(0, |idx| idx + 1).max(min_capacity);
+    if keep < self.len() { self.resize(keep) }
+    self.data.shrink_to(min_capacity);
+    self.meta.shrink_to(min_capacity);
+    if let Some(recency) = &mut self.recency { recency.shrink_to(min_capacity) }
+    if let Some(refcounts) = &mut self.refcounts { refcounts.shrink_to(min_capacity) }
+    if let Some(stack) = &mut self.free_stack { stack.shrink_to(min_capacity) }
+    self.bitmap.shrink_to_fit();
+  }
+
+  /// Returns the number of heap bytes held by the pond, including spare capacity and bitmap overhead.
+  pub fn memory_usage(&self) -> usize {
+    let meta = self.meta.capacity() * size_of::<M>();
+    let recency = self.recency.as_ref().map_or(0, |recency| recency.capacity() * size_of::<u64>());
+    let refcounts = self.refcounts.as_ref().map_or(0, |refcounts| refcounts.capacity() * size_of::<u32>());
+    let stack = self.free_stack.as_ref().map_or(0, |stack| stack.capacity() * size_of::<usize>());
+    self.data.capacity() * size_of::<MaybeUninit<T>>() + meta + recency + refcounts + stack + self.bitmap.memory_usage()
+  }
+
+  /// Writes a zero byte into every page of the slot allocation (spare capacity included) that isn't holding a value,
+  /// so the OS commits the memory now rather than on first touch. Call it after [Pond::try_reserve] or [Pond::resize].
+  ///
+  /// Assumes 4KiB pages, which covers larger pages too. This doesn't pin anything, so the OS is still free
+  /// to swap or reclaim the pages later, and the metadata and bitmap allocations aren't touched.
+  pub fn prefault(&mut self) {
+    const PAGE: usize = 4096;
+    let size = size_of::<T>();
+    if size == 0 { return }
+    let base = self.data.as_mut_ptr() as *mut u8;
+    for offset in (0 .. self.data.capacity() * size).step_by(PAGE) {
+      let slot = offset / size;
+      // Occupied slots were written when they were filled, so their pages are already committed
+      if slot < self.len() && self.bitmap.is_set(slot) { continue }
+      // Free and spare slots are uninitialized memory we own, so scribbling on them is fine
+      unsafe { base.add(offset).write_volatile(0) }
+    }
+  }
+
+  // Explains why `idx` doesn't hold a value, or Ok if it does
+  fn check_slot(&self, idx: usize) -> Result<(), SlotError> {
+    if idx >= self.len() { return Err(SlotError::OutOfBounds { idx, len: self.len() }) }
+    if !self.bitmap.is_set(idx) { return Err(SlotError::Vacant { idx }) }
+    Ok(())
+  }
+
+  /// Like [Pond::get], but the error says whether `idx` was out of bounds or just vacant.
+  pub fn get_checked(&self, idx: usize) -> Result<&T, SlotError> {
+    self.check_slot(idx)?;
+    Ok( unsafe { self.data[idx].assume_init_ref() } )
   }
 
   /// Returns an immutable reference to the data stored at the requested index, or None if the index isn't reserved
@@ -73,21 +560,176 @@ impl<T> Pond<T> {
     Some( unsafe { self.data[idx].assume_init_mut() } )
   }
 
+  /// Returns a mutable reference to the data stored at `idx`, first [Pond::write]ing the result of `f` if the slot is free.
+  ///
+  /// # Panics
+  /// If the slot is free and past the cap of a pond made with [Pond::with_eviction].
+  pub fn get_or_insert_with<F: FnOnce() -> T>(&mut self, idx: usize, f: F) -> &mut T {
+    if !self.is_occupied(idx) {
+      self.assert_under_cap(idx, "get_or_insert_with");
+      self.write(idx, f());
+    }
+    unsafe { self.data[idx].assume_init_mut() }
+  }
+
+  /// Returns a mutable reference to the data stored at `idx`, first [Pond::write]ing `value` if the slot is free.
+  /// If the slot is already occupied its value is kept and `value` is dropped.
+  pub fn get_or_insert_at(&mut self, idx: usize, value: T) -> &mut T { self.get_or_insert_with(idx, || value) }
+
+  /// Returns the metadata stored alongside the value at `idx`, or None if the index isn't reserved
+  pub fn meta(&self, idx: usize) -> Option<&M> {
+    if !self.is_occupied(idx) { return None }
+    Some(&self.meta[idx])
+  }
+
+  /// Returns a mutable reference to the metadata stored alongside the value at `idx`, or None if the index isn't reserved
+  pub fn meta_mut(&mut self, idx: usize) -> Option<&mut M> {
+    if !self.is_occupied(idx) { return None }
+    Some(&mut self.meta[idx])
+  }
+
+  /// Returns immutable references to the data stored at each requested index, or None if any index isn't reserved.
+  /// Indices may repeat.
+  pub fn get_many<const N: usize>(&self, indices: [usize; N]) -> Option<[&T; N]> {
+    if !indices.iter().all(|idx| self.is_occupied(*idx)) { return None }
+    Some( indices.map(|idx| unsafe { self.data[idx].assume_init_ref() }) )
+  }
+
+  /// Looks up each of `indices` in turn, yielding None for any which aren't reserved (or are out of bounds).
+  /// `indices` should be sorted, so the lookups walk forwards through memory in a single pass.
+  pub fn get_many_sorted<'a>(&'a self, indices: &'a [usize]) -> impl Iterator<Item = Option<&'a T>> {
+    debug_assert!(indices.is_sorted(), "get_many_sorted was given unsorted indices");
+    indices.iter().map(|idx| self.get(*idx))
+  }
+
+  /// Returns mutable references to the data at `a` and `b`, or None if they're the same index or either isn't reserved.
+  pub fn get_pair_mut(&mut self, a: usize, b: usize) -> Option<(&mut T, &mut T)> {
+    if !self.is_occupied(a) || !self.is_occupied(b) { return None }
+    let [a, b] = self.data.get_disjoint_mut([a, b]).ok()?;
+    Some( unsafe { (a.assume_init_mut(), b.assume_init_mut()) } )
+  }
+
+  /// Returns mutable references to the data at `a` and `b`, for when anything else is a bug.
+  ///
+  /// # Panics
+  /// If `a` and `b` are the same index, or either isn't reserved. See [Pond::get_pair_mut] for a fallible version.
+  pub fn index_two_mut(&mut self, a: usize, b: usize) -> (&mut T, &mut T) {
+    assert_ne!(a, b, "index_two_mut was given the same index twice");
+    for idx in [a, b] { assert!(self.is_occupied(idx), "index_two_mut was given unoccupied index {idx}") }
+    self.get_pair_mut(a, b).unwrap()
+  }
+
   /// Stores `data` in PoolField, returning a reference index.
   #[must_use]
-  pub fn insert(&mut self, data:T) -> usize {
-    let idx = self.reserve();
+  pub fn insert(&mut self, data:T) -> usize { self.insert_evicting(data).0 }
+
+  /// Stores `data` in PoolField, returning a reference index alongside the value evicted to make room for it.
+  /// Only ponds made with [Pond::with_eviction] ever evict, and only once they're at capacity.
+  ///
+  /// Finding the oldest value walks every occupied slot, so a full cache pays O(n) per insert.
+  #[must_use]
+  pub fn insert_evicting(&mut self, data: T) -> (usize, Option<T>) {
+    let (idx, evicted) = self.reserve_evicting();
     self.data[idx].write(data);
+    (idx, evicted)
+  }
+
+  // Frees the least recently reserved slot if the next insert would otherwise go past the eviction cap
+  fn evict_oldest(&mut self) -> Option<T> {
+    if self.under_cap(self.next_index()) { return None }
+    self.evict_oldest_where(|_| true).map(|(_, value)| value)
+  }
+
+  // Frees the least recently reserved slot under the cap that `allowed` accepts, returning where it was
+  fn evict_oldest_where(&mut self, allowed: impl Fn(usize) -> bool) -> Option<(usize, T)> {
+    let recency = self.recency.as_ref()?;
+    let cap = self.eviction_cap?;
+    let oldest = self.bitmap.iter_set().take_while(|idx| *idx < cap).filter(|idx| allowed(*idx)).min_by_key(|idx| recency[*idx])?;
+    Some( (oldest, self.free(oldest)?) )
+  }
+  
+  /// Stores `data` alongside its metadata, returning a reference index.
+  #[must_use]
+  pub fn insert_tagged(&mut self, data: T, meta: M) -> usize {
+    let idx = self.insert(data);
+    self.meta[idx] = meta;
+    idx
+  }
+
+  /// Stores `data` in the first free slot at or after `hint`, returning its index. If every slot from `hint` on
+  /// is full (or `hint` is beyond [Pond::len]) this falls back to wherever [Pond::insert] would put it.
+  ///
+  /// Useful for keeping related nodes (a parent and its children) close together in memory.
+  #[must_use]
+  pub fn insert_near(&mut self, hint: usize, data:T) -> usize {
+    let found = self.bitmap.first_free_from(hint).filter(|idx| *idx < self.len() && self.under_cap(*idx));
+    let Some(idx) = found else { return self.insert(data) };
+    self.write(idx, data);
     idx
   }
   
+  /// Stores `data` in the first free slot whose index is a multiple of `align`, growing the pond if there isn't one.
+  /// Smaller free slots in between are skipped.
+  ///
+  /// Ponds made with [Pond::with_eviction] evict the oldest aligned value instead of growing past their cap.
+  ///
+  /// # Panics
+  /// If `align` is 0.
+  #[must_use]
+  pub fn insert_aligned(&mut self, align: usize, data: T) -> usize {
+    let mut idx = self.bitmap.first_free_aligned(align).unwrap_or(self.len().next_multiple_of(align));
+    // Slot 0 is always aligned and under the cap, so there's always something to evict
+    if !self.under_cap(idx) { idx = self.evict_oldest_where(|idx| idx.is_multiple_of(align)).unwrap().0 }
+    self.write(idx, data);
+    idx
+  }
+
+  /// Marks the first `n` free slots as occupied without initializing them, returning their indices.
+  /// The pond grows if there aren't enough free slots.
+  ///
+  /// # Safety
+  /// The returned slots hold uninitialized memory. Each one must be initialized through
+  /// [Pond::unsafe_data_mut] before it's read, written, freed, or serialized.
+  #[must_use]
+  pub unsafe fn reserve_n(&mut self, n: usize) -> Vec<usize> {
+    (0 .. n).map(|_| self.reserve()).collect()
+  }
+
+  /// Marks the next free slot as occupied without initializing it, handing back its index alongside the slot itself
+  /// so large values can be built in place rather than moved in.
+  ///
+  /// # Safety
+  /// The slot is uninitialized. It must be written through the returned [MaybeUninit] before it's read, written,
+  /// freed, or serialized.
+  #[must_use]
+  pub unsafe fn reserve_uninit(&mut self) -> (usize, &mut MaybeUninit<T>) {
+    let idx = self.reserve();
+    (idx, &mut self.data[idx])
+  }
+
+  /// Starts a [Transaction], which frees everything inserted through it unless it's committed.
+  pub fn begin(&mut self) -> Transaction<'_, T, M> { Transaction::new(self) }
+
+  /// Claims the slot the next [Pond::insert] would use, for a value which might fail to build.
+  /// Either [SlotToken::commit] a value to it or let the token go to free the slot.
+  pub fn reserve_slot(&mut self) -> SlotToken<'_, T, M> { SlotToken::new(self) }
+
+  /// Creates a [Cursor] over the occupied slots, which can free and insert values as it goes.
+  pub fn cursor(&mut self) -> Cursor<'_, T, M> { Cursor::new(self) }
+
   /// Overwrite and reserve the data at `idx`. 
   /// Returns Some(old_data) or None, depending whether the slot was previously reserved.
   ///
   /// This function will [Pond::resize] if `idx` is beyond [Pond::len], guaranteeing
   /// your data will be written to the requested slot.
   pub fn write(&mut self, idx:usize, new_data:T) -> Option<T> {
-    if idx >= self.len() { self.resize(idx + 1) }
+    debug_assert!(!(self.strict && self.is_occupied(idx)), "strict pond wrote over occupied slot {idx} without freeing it");
+    self.overwrite(idx, new_data)
+  }
+
+  // Write without the strict mode check
+  fn overwrite(&mut self, idx: usize, new_data: T) -> Option<T> {
+    if idx >= self.len() { self.grow_to_include(idx) }
     let old_value = if self.is_occupied(idx) { 
       Some( unsafe { self.data[idx].assume_init_read() } ) 
     } else { None };
@@ -96,12 +738,158 @@ impl<T> Pond<T> {
     old_value
   }
 
+  /// [Pond::write]s `data` to `idx`, dropping whatever used to be there, and returns a mutable reference to it.
+  pub fn set(&mut self, idx: usize, data: T) -> &mut T {
+    self.overwrite(idx, data);
+    unsafe { self.data[idx].assume_init_mut() }
+  }
+
   /// Frees the data at `index`, returning it on success or None on failure.
   /// Failure means you were trying to free a node which was already free.
+  ///
+  /// Ponds made with [Pond::new_strict] panic if `idx` is past [Pond::len] in debug builds.
   pub fn free(&mut self, idx:usize) -> Option<T> {
+    debug_assert!(!(self.strict && idx >= self.len()), "strict pond freed slot {idx}, which is out of bounds for a pond of length {}", self.len());
     if !self.is_occupied(idx) { return None }
+    let data = unsafe { self.data[idx].assume_init_read() };
     self.mark_free(idx);
-    Some( unsafe { self.data[idx].assume_init_read() } )
+    Some(data)
+  }
+
+  /// Takes out another handle to the value at `idx`, returning `idx` back or None if the slot is free
+  /// or the pond wasn't made with [Pond::with_refcounting].
+  pub fn clone_handle(&mut self, idx: usize) -> Option<usize> {
+    if !self.is_occupied(idx) { return None }
+    let count = &mut self.refcounts.as_mut()?[idx];
+    *count = count.checked_add(1).expect("too many handles to a single slot");
+    Some(idx)
+  }
+
+  /// Gives back a handle to the value at `idx`, freeing and returning the value once the last handle is gone.
+  /// Ponds which don't count handles free the value straight away, like [Pond::free].
+  pub fn free_handle(&mut self, idx: usize) -> Option<T> {
+    if !self.is_occupied(idx) { return None }
+    if let Some(refcounts) = &mut self.refcounts {
+      refcounts[idx] -= 1;
+      if refcounts[idx] != 0 { return None }
+    }
+    self.free(idx)
+  }
+
+  /// Returns how many handles are out for the value at `idx`, if the slot is occupied and the pond counts them.
+  pub fn handle_count(&self, idx: usize) -> Option<u32> {
+    if !self.is_occupied(idx) { return None }
+    Some( self.refcounts.as_ref()?[idx] )
+  }
+
+  /// Like [Pond::free], but the error says whether `idx` was out of bounds or just vacant.
+  pub fn free_checked(&mut self, idx: usize) -> Result<T, SlotError> {
+    self.check_slot(idx)?;
+    Ok( self.free(idx).unwrap() )
+  }
+
+  /// Frees every occupied slot whose bit is set in `mask` (laid out like [Pond::occupancy_words]),
+  /// returning their values in index order. The bitmap is updated a word at a time rather than a slot at a time.
+  pub fn free_mask(&mut self, mask: &[u64]) -> Vec<T> {
+    let selected: Vec<u64> = self.bitmap.words().iter().zip(mask).map(|(word, mask)| word & mask).collect();
+    let mut freed = Vec::with_capacity(selected.iter().map(|word| word.count_ones() as usize).sum());
+    for idx in set_bits(selected.iter().copied()) {
+      freed.push( unsafe { self.data[idx].assume_init_read() } );
+      self.release(idx);
+    }
+    self.bitmap.clear_mask(&selected);
+    freed
+  }
+
+  /// Hands each occupied value to `f` by value, freeing the slot if `f` returns None or
+  /// writing the returned value back in its place. Kept values keep their metadata, recency, and handles.
+  ///
+  /// Slots are marked free while `f` holds their value, so if `f` panics the slot it was working on is left free.
+  pub fn retain_map<F: FnMut(usize, T) -> Option<T>>(&mut self, mut f: F) {
+    let mut search_from = 0;
+    while let Some(idx) = self.next_occupied(search_from) {
+      search_from = idx + 1;
+      self.bitmap.set(idx, false);
+      self.occupied -= 1;
+      self.free_hint = self.free_hint.min(idx);
+      let value = unsafe { self.data[idx].assume_init_read() };
+      if let Some(new_value) = f(idx, value) {
+        self.data[idx].write(new_value);
+        self.bitmap.set(idx, true);
+        self.occupied += 1;
+      } else {
+        // Let mark_free finish the job, scrubbing and stacking the slot like any other free
+        self.occupied += 1;
+        self.mark_free(idx);
+      }
+    }
+  }
+
+  /// Calls `f` on each occupied slot in index order, handing it the slot's value mutably alongside the values at
+  /// `idx + offset` for each of `offsets`. Neighbors which are free, out of bounds, or the slot itself come through as None.
+  ///
+  /// Values are updated in place, so neighbors visited earlier in the walk show their new values.
+  pub fn for_each_with_neighbors<F: FnMut(usize, &mut T, &[Option<&T>])>(&mut self, offsets: &[isize], mut f: F) {
+    let data = self.data.as_mut_ptr();
+    let len = self.len();
+    let bitmap = &self.bitmap;
+    let mut neighbors = Vec::with_capacity(offsets.len());
+    for idx in bitmap.iter_set() {
+      neighbors.clear();
+      neighbors.extend(offsets.iter().map(|offset| {
+        let neighbor = idx.checked_add_signed(*offset).filter(|n| *n != idx && *n < len && bitmap.is_set(*n))?;
+        Some( unsafe { (*data.add(neighbor)).assume_init_ref() } )
+      }));
+      // The neighbors never include idx, so this is the only reference to its slot
+      f(idx, unsafe { (*data.add(idx)).assume_init_mut() }, &neighbors);
+    }
+  }
+
+  /// Frees every occupied slot `f` picks out, returning their values alongside the indices they came from, in index order.
+  /// The rest of the pond is left as it was.
+  pub fn drain_filter<F: FnMut(usize, &T) -> bool>(&mut self, mut f: F) -> Vec<(usize, T)> {
+    let mut drained = Vec::new();
+    let mut search_from = 0;
+    while let Some(idx) = self.next_occupied(search_from) {
+      search_from = idx + 1;
+      if f(idx, unsafe { self.data[idx].assume_init_ref() }) { drained.push( (idx, self.free(idx).unwrap()) ) }
+    }
+    drained
+  }
+
+  /// Frees every occupied slot within `range` as it's iterated, yielding their values alongside their indices in order.
+  /// Slots outside of `range` aren't touched, and anything left in `range` is freed if the iterator is dropped early.
+  pub fn drain_range(&mut self, range: Range<usize>) -> DrainRange<'_, T, M> { DrainRange::new(self, range) }
+
+  /// Drops every value in the pond while keeping its [Pond::len], so every slot is free but still in bounds
+  /// and [Pond::write]s within the old range won't resize. Capacity is kept too.
+  pub fn reset(&mut self) {
+    let len = self.len();
+    self.resize(0);
+    self.resize(len);
+  }
+
+  /// Drops every value in the pond, then fills slots 0, 1, 2... from `values`, leaving nothing free between them.
+  /// The existing allocation is reused, so rebuilding a pond of a similar size each frame doesn't reallocate.
+  pub fn replace_all<I: IntoIterator<Item = T>>(&mut self, values: I) {
+    self.resize(0);
+    let values = values.into_iter();
+    self.data.reserve(values.size_hint().0);
+    for value in values {
+      let idx = self.len();
+      self.grow_to_include(idx);
+      self.data[idx].write(value);
+    }
+    self.mark_reserved_front(self.len());
+  }
+
+  /// Moves every occupied value into `out` in index order, leaving the pond empty but keeping its capacity.
+  pub fn drain_into(&mut self, out: &mut Vec<T>) {
+    out.reserve(self.count());
+    for idx in 0 .. self.len() {
+      if let Some(value) = self.free(idx) { out.push(value) }
+    }
+    self.resize(0);
   }
 
   /// Travels through memory and re-arranges slots so that they are contiguous in memory, with no free slots in between occupied ones.
@@ -113,23 +901,114 @@ impl<T> Pond<T> {
   #[must_use]
   pub fn defrag(&mut self) -> HashMap<usize, usize> {
     let mut remapped = HashMap::new();
-    if self.len() == 0 { return remapped }
-    let mut full = self.len();
-    let mut last_full = full;
-    while let Some(free) = self.bitmap.first_free() {
-      for idx in (free .. last_full).rev() {
-        if self.bitmap.is_set(idx) { full = idx; break }
-      }
-      if full == last_full { break }
+    self.defrag_into(&mut remapped);
+    remapped
+  }
+
+  /// [Pond::defrag]s, writing the remap into `remap` (after clearing it) so its allocation can be reused across calls.
+  pub fn defrag_into(&mut self, remap: &mut HashMap<usize, usize>) {
+    remap.clear();
+    self.defrag_range_into(0 .. self.len(), remap);
+  }
+
+  /// [Pond::defrag]s only the slots within `range`, moving occupied slots from its end into free slots
+  /// towards its start. Nothing outside of `range` is touched, so a large pond can be compacted a piece at a time.
+  #[must_use]
+  pub fn defrag_range(&mut self, range: Range<usize>) -> HashMap<usize, usize> {
+    let mut remapped = HashMap::new();
+    self.defrag_range_into(range, &mut remapped);
+    remapped
+  }
+
+  fn defrag_range_into(&mut self, range: Range<usize>, remapped: &mut HashMap<usize, usize>) {
+    let mut last_full = range.end.min(self.len());
+    let mut search_from = range.start;
+    while let Some(free) = self.bitmap.first_free_from(search_from).filter(|free| *free < last_full) {
+      let Some(full) = (free .. last_full).rev().find(|idx| self.bitmap.is_set(*idx)) else { break };
       remapped.insert(full, free);
-      self.data.swap(free, full);
-      self.bitmap.set(full, false);
-      self.bitmap.set(free, true);
+      self.swap_slots(full, free);
       last_full = full;
+      search_from = free + 1;
+    }
+  }
+
+  /// Frees every value `f` rejects while sliding the survivors down to the front of the pond in one pass,
+  /// returning where they moved (Key:Old, Value:New). Unlike [Pond::defrag], survivors keep their relative order.
+  pub fn retain_compact<F: FnMut(usize, &T) -> bool>(&mut self, mut f: F) -> HashMap<usize, usize> {
+    let mut remapped = HashMap::new();
+    // Everything before `next` is packed, everything from it up to the slot we're on is free
+    let mut next = 0;
+    let mut search_from = 0;
+    while let Some(idx) = self.next_occupied(search_from) {
+      search_from = idx + 1;
+      if !f(idx, unsafe { self.data[idx].assume_init_ref() }) {
+        self.free(idx);
+        continue
+      }
+      if idx != next {
+        self.swap_slots(idx, next);
+        remapped.insert(idx, next);
+      }
+      next += 1;
     }
     remapped
   }
 
+  /// Moves every occupied slot from `idx` to `idx + offset`, growing the pond by `offset`, and returns the remap
+  /// (Key:Old, Value:New). Handy for making room to merge another pond's indices in below.
+  ///
+  /// # Panics
+  /// If the shifted pond would be too long to index.
+  #[must_use]
+  pub fn shift(&mut self, offset: usize) -> HashMap<usize, usize> {
+    if offset == 0 { return HashMap::new() }
+    let occupied: Vec<usize> = self.bitmap.iter_set_rev().collect();
+    let size = self.len().checked_add(offset)
+      .unwrap_or_else(|| panic!("shifting {} slots by {offset} is past the largest index a pond can hold", self.len()));
+    self.resize(size);
+    // Going from the top down, each slot's destination has always been vacated already
+    occupied.into_iter().map(|idx| {
+      self.swap_slots(idx, idx + offset);
+      (idx, idx + offset)
+    }).collect()
+  }
+
+  /// Swaps whatever is at `idx` (which may be free) with the value at [Pond::last_occupied], returning where
+  /// the latter came from. Returns None if nothing is occupied after `idx`.
+  ///
+  /// Filling holes this way and then calling [Pond::shrink_to_occupancy] makes for a hand-rolled [Pond::trim].
+  pub fn swap_with_last_occupied(&mut self, idx: usize) -> Option<usize> {
+    let last = self.last_occupied().filter(|last| *last > idx)?;
+    self.swap_slots(idx, last);
+    Some(last)
+  }
+
+  /// Moves whatever is in slot `i` (value or not) to slot `perm[i]`, following each cycle of swaps in place.
+  /// Handy for applying a reordering computed elsewhere, like a spatial sort, without [Pond::defrag]'s hole filling.
+  ///
+  /// # Panics
+  /// If `perm` isn't [Pond::len] long, or doesn't use every index below it exactly once.
+  pub fn apply_permutation(&mut self, perm: &[usize]) {
+    assert_eq!(perm.len(), self.len(), "permutation covers {} slots but the pond has {}", perm.len(), self.len());
+    let mut visited = vec![false; perm.len()];
+    for &target in perm {
+      assert!(target < perm.len() && !visited[target], "{target} isn't a valid target for the permutation");
+      visited[target] = true;
+    }
+    visited.fill(false);
+    for start in 0 .. perm.len() {
+      if visited[start] { continue }
+      visited[start] = true;
+      // Slot `start` holds whatever was at the cycle's previous stop, which gets sent on to the next one
+      let mut next = perm[start];
+      while next != start {
+        self.swap_slots(start, next);
+        visited[next] = true;
+        next = perm[next];
+      }
+    }
+  }
+
   /// [Pond::defrag]s the memory, then shrinks the internal vec to fit remaining data.
   #[must_use]
   pub fn trim(&mut self) -> HashMap<usize, usize> {
@@ -138,6 +1017,36 @@ impl<T> Pond<T> {
     remap
   }
 
+  /// Returns the remap (Key:Old, Value:New) [Pond::trim] would produce right now, without moving anything.
+  /// Handy for staging external fixups, or deciding the churn isn't worth it.
+  pub fn preview_trim(&self) -> HashMap<usize, usize> {
+    let mut remapped = HashMap::new();
+    // Pairs the holes from the front with the values from the back, just like defrag does
+    let mut fulls = self.bitmap.iter_set_rev();
+    let mut free = self.next_free(0);
+    while let Some(full) = fulls.next().filter(|full| *full > free) {
+      remapped.insert(full, free);
+      free = self.next_free(free + 1);
+    }
+    remapped
+  }
+
+  /// Drops the free slots after [Pond::last_occupied] without moving anything, so holes before it stay put.
+  /// Pair with [Pond::shrink_to_fit] to hand the memory back.
+  pub fn shrink_to_occupancy(&mut self) { self.resize(self.last_occupied().map_or(0, |idx| idx + 1)) }
+
+  /// Returns how many free slots sit after [Pond::last_occupied], which is what [Pond::shrink_to_occupancy] would drop.
+  pub fn trailing_free(&self) -> usize { self.len() - self.last_occupied().map_or(0, |idx| idx + 1) }
+
+  /// [Pond::trim]s, then patches every index in `indices` to point at where its value ended up.
+  /// Takes anything which yields `&mut usize`, like `&mut Vec<usize>` or `[&mut a, &mut b]`.
+  pub fn trim_and_fixup<'a>(&mut self, indices: impl IntoIterator<Item = &'a mut usize>) {
+    let remap = self.trim();
+    for idx in indices {
+      if let Some(new) = remap.get(idx) { *idx = *new }
+    }
+  }
+
   /// Returns a safe, readonly version of the internal vec.
   pub fn safe_data(&self) -> Vec<Option<&T>> {
     let mut safe_data = Vec::with_capacity(self.data.len());
@@ -145,59 +1054,400 @@ impl<T> Pond<T> {
     safe_data
   }
 
+  /// Clears `out` and fills it with every occupied (item_idx, &T) in index order, reusing its allocation.
+  /// Cheaper than [Pond::safe_data] for something which runs every frame.
+  pub fn collect_occupied_into<'a>(&'a self, out: &mut Vec<(usize, &'a T)>) {
+    out.clear();
+    out.extend(self.iter());
+  }
+
+  /// Moves every value out into a vec of [Pond::len] entries, with None standing in for free slots.
+  pub fn into_option_vec(mut self) -> Vec<Option<T>> {
+    let data = std::mem::take(&mut self.data);
+    data.into_iter().enumerate().map(|(idx, slot)| {
+      if self.bitmap.is_set(idx) { Some( unsafe { slot.assume_init() } ) } else { None }
+    }).collect()
+  }
+
+  /// Checks the pond's internal bookkeeping, returning a description of the first broken invariant.
+  ///
+  /// This walks every slot and rebuilds the bitmap's acceleration layers to compare against,
+  /// so it's meant for `debug_assert!`s and tests rather than hot paths.
+  pub fn validate(&self) -> Result<(), String> {
+    self.bitmap.validate(self.len())?;
+    let counted = self.bitmap.count_set();
+    if counted != self.occupied { return Err(format!("pond thinks {} slots are occupied but {counted} are", self.occupied)) }
+    if let Some(free) = self.bitmap.first_free().filter(|free| *free < self.free_hint) {
+      return Err(format!("pond thinks every slot before {} is occupied but {free} is free", self.free_hint))
+    }
+    Ok(())
+  }
+
+  /// The raw occupancy bits, one per slot. Slot `idx` lives in bit `idx % 64` (counting from the least significant bit)
+  /// of word `idx / 64`, and bits past [Pond::len] are always unset.
+  pub fn occupancy_words(&self) -> &[u64] { self.bitmap.words() }
+
+  /// Draws the occupancy as one character per slot, `#` for occupied and `.` for free, with a space after every 8 slots.
+  /// Meant for eyeballing fragmentation while debugging.
+  pub fn debug_occupancy_string(&self) -> String {
+    let mut drawn = String::with_capacity(self.len() + self.len() / 8);
+    for idx in 0 .. self.len() {
+      if idx != 0 && idx % 8 == 0 { drawn.push(' ') }
+      drawn.push(if self.bitmap.is_set(idx) { '#' } else { '.' });
+    }
+    drawn
+  }
+
+  /// An owned copy of [Pond::occupancy_words], with the same bit ordering, which can be held onto across mutations.
+  pub fn occupancy_mask(&self) -> Vec<u64> { self.bitmap.words().to_vec() }
+
+  /// Replaces the occupancy bits with `words`, laid out like [Pond::occupancy_words], without touching any values.
+  /// Bits past [Pond::len] are ignored and missing words count as free. Metadata is left as it was,
+  /// other than newly occupied slots starting with one handle on refcounting ponds.
+  ///
+  /// # Safety
+  /// Every slot set in `words` must hold an initialized value. Slots which go from occupied to free are
+  /// forgotten rather than dropped, so anything they own has to be dealt with beforehand.
+  pub unsafe fn set_occupancy_raw(&mut self, words: &[u64]) {
+    let mut base = words.to_vec();
+    base.resize(self.len().div_ceil(64), 0);
+    if let Some(refcounts) = &mut self.refcounts {
+      let fresh = base.iter().zip(self.bitmap.words()).map(|(new, old)| new & !old);
+      let len = refcounts.len();
+      for idx in set_bits(fresh).take_while(|idx| *idx < len) { refcounts[idx] = 1 }
+    }
+    self.bitmap.rebuild_from_base(base);
+    self.bitmap.resize(self.len());
+    self.free_hint = 0;
+    self.occupied = self.bitmap.count_set();
+    self.high_water = self.high_water.max(self.occupied);
+  }
+
+  /// Captures which slots are currently occupied, to later [OccupancySnapshot::diff] against.
+  pub fn occupancy_snapshot(&self) -> OccupancySnapshot { OccupancySnapshot::new(self.bitmap.words()) }
+
+  /// Returns every value as one slice if they're packed at the front of the pond (after a [Pond::trim] say),
+  /// or None if there's a free slot between any of them.
+  pub fn as_contiguous_slice(&self) -> Option<&[T]> {
+    if !self.is_packed() { return None }
+    // Every slot up to count is occupied, and MaybeUninit<T> is laid out just like T
+    Some( unsafe { std::slice::from_raw_parts(self.data.as_ptr() as *const T, self.count()) } )
+  }
+
+  /// The mutable version of [Pond::as_contiguous_slice].
+  pub fn as_contiguous_slice_mut(&mut self) -> Option<&mut [T]> {
+    if !self.is_packed() { return None }
+    Some( unsafe { std::slice::from_raw_parts_mut(self.data.as_mut_ptr() as *mut T, self.count()) } )
+  }
+
+  /// Returns true if the occupied slots are exactly 0 .. [Pond::count], meaning [Pond::as_contiguous_slice] will succeed.
+  pub fn is_packed(&self) -> bool { self.bitmap.first_free().unwrap_or(self.len()) == self.count() }
+
+  /// Returns a pointer to the first slot, for handing the pond to code (like a C consumer) which reads it directly.
+  /// Slot `idx` is at `as_ptr().add(idx)`, and is only initialized if [Pond::occupancy_words] says it's occupied.
+  ///
+  /// The pointer is invalidated by anything which takes `&mut self`, since inserting, resizing, or defragging
+  /// can reallocate the slots or move values between them. Reading through it is up to you to get right.
+  ///
+  /// The slots are aligned like `T`, whatever the allocation. If the backing memory needs a stricter alignment
+  /// (page aligned for DMA, say) pool a wrapper carrying that alignment instead:
+  /// ```
+  /// use lilypads::Pond;
+  ///
+  /// #[repr(C, align(4096))]
+  /// struct Page([u8; 4096]);
+  ///
+  /// let mut pool = Pond::new();
+  /// let _ = pool.insert(Page([0; 4096]));
+  /// assert_eq!(pool.as_ptr() as usize % 4096, 0);
+  /// ```
+  pub fn as_ptr(&self) -> *const MaybeUninit<T> { self.data.as_ptr() }
+
   /// Returns the readonly, unsafe reference to the internal vec. 
   /// This should only be used when you have some sort of
   /// access scheme (such as a tree) which can be used to safely navigate the unsafe data
   pub fn unsafe_data(&self) -> &Vec<MaybeUninit<T>> { &self.data }
+
+  /// Returns the mutable, unsafe reference to the internal slots.
+  ///
+  /// # Safety
+  /// Every occupied slot must still hold an initialized value once you're done with the slice.
+  pub unsafe fn unsafe_data_mut(&mut self) -> &mut [MaybeUninit<T>] { &mut self.data }
+
+  /// Takes the pond apart into its slots and occupancy words (laid out like [Pond::occupancy_words]),
+  /// without moving or dropping any values. Metadata and any other per-slot tracking is dropped.
+  ///
+  /// Nothing will drop the occupied values from here on, put them back with [Pond::from_raw_parts] or drop them yourself.
+  pub fn into_raw_parts(self) -> (Vec<MaybeUninit<T>>, Vec<u64>) { (self.data, self.bitmap.into_words()) }
+
+  /// Rebuilds a pond from slots and occupancy words, like the ones [Pond::into_raw_parts] hands out.
+  /// The pond is `data.len()` slots long, and bits in `words` past that are ignored.
+  ///
+  /// # Safety
+  /// Every slot set in `words` must hold an initialized value.
+  pub unsafe fn from_raw_parts(data: Vec<MaybeUninit<T>>, words: Vec<u64>) -> Self {
+    let mut pond = Self { data, ..Self::empty() };
+    pond.resize(pond.data.len());
+    unsafe { pond.set_occupancy_raw(&words) };
+    pond
+  }
+}
+
+impl<T: Default, M: Default> Pond<T, M> {
+  /// Returns a mutable reference to the data stored at `idx`, filling the slot with `T::default()` if it's free.
+  pub fn get_or_default(&mut self, idx: usize) -> &mut T { self.get_or_insert_with(idx, T::default) }
+
+  /// Fills the first run of `n` free slots with `T::default()`, growing the pond if there isn't one,
+  /// and returns where the run starts alongside the run itself to edit as a slice.
+  ///
+  /// # Panics
+  /// If the run would pass the cap of a pond made with [Pond::with_eviction].
+  pub fn reserve_block_default(&mut self, n: usize) -> (usize, &mut [T]) {
+    let start = self.free_run(n);
+    if n > 0 { self.assert_under_cap(start + n - 1, "reserve_block_default") }
+    if start.saturating_add(n) > self.len() { self.grow_to_include(start.saturating_add(n - 1)) }
+    for idx in start .. start + n { self.overwrite(idx, T::default()); }
+    // Every slot in the run was just filled, and MaybeUninit<T> is laid out just like T
+    (start, unsafe { std::slice::from_raw_parts_mut(self.data.as_mut_ptr().add(start) as *mut T, n) })
+  }
+
+  /// Like [Pond::get_or_default], but growing the pond to reach `idx` fills every new slot on the way with
+  /// `T::default()` too, so it behaves like a dense array with default gaps.
+  ///
+  /// # Panics
+  /// If `idx` is free and past the cap of a pond made with [Pond::with_eviction].
+  pub fn get_mut_or_fill(&mut self, idx: usize) -> &mut T {
+    let len = self.len();
+    if idx >= len {
+      self.assert_under_cap(idx, "get_mut_or_fill");
+      self.grow_to_include(idx);
+      for slot in len ..= idx { self.overwrite(slot, T::default()); }
+    }
+    self.get_or_default(idx)
+  }
+}
+impl<T: PartialEq, M: Default> Pond<T, M> {
+  /// Returns the index of a value equal to `value` if one is already pooled, otherwise [Pond::insert]s it.
+  /// Scans every occupied slot, see [Pond::insert_unique_indexed] for a hashed version.
+  pub fn insert_unique(&mut self, value: T) -> usize {
+    let existing = self.iter().find(|(_, val)| **val == value).map(|(idx, _)| idx);
+    existing.unwrap_or_else(|| self.insert(value))
+  }
+}
+impl<T: Hash + Eq + Clone, M: Default> Pond<T, M> {
+  /// [Pond::insert_unique], but looks values up through `index` (Key:Value, Value:Index) instead of scanning,
+  /// adding an entry whenever something new is inserted. The pond doesn't see the map otherwise, so entries
+  /// whose slot was since freed or changed are treated as missing and replaced.
+  pub fn insert_unique_indexed(&mut self, value: T, index: &mut HashMap<T, usize>) -> usize { self.insert_or_get(value, index).0 }
+
+  /// [Pond::insert_unique_indexed], which also returns whether `value` was new to the pond (true) or already pooled (false).
+  pub fn insert_or_get(&mut self, value: T, index: &mut HashMap<T, usize>) -> (usize, bool) {
+    if let Some(&idx) = index.get(&value) && self.get(idx) == Some(&value) { return (idx, false) }
+    let idx = self.insert(value.clone());
+    index.insert(value, idx);
+    (idx, true)
+  }
+}
+impl<T: Clone, M: Default> Pond<T, M> {
+  /// Clones every value alongside where it sits, so the pond can be [Pond::restore]d to exactly this state later.
+  pub fn snapshot(&self) -> Snapshot<T> {
+    Snapshot {
+      len: self.len(),
+      words: self.bitmap.words().to_vec(),
+      values: self.iter().map(|(_, val)| val.clone()).collect(),
+    }
+  }
+
+  /// Builds a fresh pond holding clones of every value, packed to the front in ascending index order,
+  /// alongside where each moved value ended up (Key:Old, Value:New). The pond itself isn't touched.
+  pub fn compacted(&self) -> (Pond<T>, HashMap<usize, usize>) {
+    let count = self.count();
+    let mut packed = Pond::empty();
+    packed.resize(count);
+    let mut remapped = HashMap::new();
+    for (new, (old, val)) in self.iter().enumerate() {
+      packed.data[new].write(val.clone());
+      if old != new { remapped.insert(old, new); }
+    }
+    packed.mark_reserved_front(count);
+    (packed, remapped)
+  }
+
+  /// Drops everything in the pond and puts back the values and indices captured by `snapshot`,
+  /// reusing the pond's existing allocation where it can.
+  pub fn restore(&mut self, snapshot: &Snapshot<T>) {
+    self.resize(0);
+    self.resize(snapshot.len);
+    for (idx, val) in set_bits(snapshot.words.iter().copied()).zip(&snapshot.values) { self.data[idx].write(val.clone()); }
+    self.mark_reserved_words(snapshot.words.clone());
+  }
+}
+
+impl<T: ?Sized, M: Default> Pond<Box<T>, M> {
+  /// Returns a reference to the boxed value at `idx`, or None if the index isn't reserved.
+  pub fn get_dyn(&self, idx: usize) -> Option<&T> { self.get(idx).map(|boxed| &**boxed) }
+
+  /// Returns a mutable reference to the boxed value at `idx`, or None if the index isn't reserved.
+  pub fn get_dyn_mut(&mut self, idx: usize) -> Option<&mut T> { self.get_mut(idx).map(|boxed| &mut **boxed) }
+}
+
+impl<T, M: Default> Default for Pond<T, M> {
+  fn default() -> Self { Self::empty() }
 }
 
 // Iterators
-impl<T> Pond<T> {
+impl<T, M: Default> Pond<T, M> {
 
-  /// Returns an iterator over all valid items stored in this pond, in order.
+  /// Returns an iterator over all valid items stored in this pond, in order. It can also be walked in reverse.
   ///
   /// This iterator covers (item_idx, &T)
-  pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
-    let bitmap = &self.bitmap;
-    self.data.iter().enumerate().filter_map(|(idx, data)| {
-      // This is a safe call because we're iterating over avaliable slots already
-      if bitmap.is_set(idx) {
-        Some( (idx, unsafe { data.assume_init_ref() }) )
-      } else { None }
+  pub fn iter(&self) -> impl DoubleEndedIterator<Item = (usize, &T)> {
+    // This is a safe call because we're only visiting occupied slots
+    self.bitmap.iter_set().map(|idx| (idx, unsafe { self.data[idx].assume_init_ref() }) )
+  }
+
+  /// Returns an iterator over all valid items stored in this pond alongside their metadata, in order.
+  ///
+  /// This iterator covers (item_idx, &T, &M)
+  pub fn iter_with_meta(&self) -> impl DoubleEndedIterator<Item = (usize, &T, &M)> {
+    self.iter().map(|(idx, val)| (idx, val, &self.meta[idx]))
+  }
+
+  /// Returns an iterator over all valid items stored in this pond, starting at `start` and wrapping back around
+  /// to the beginning, stopping before it gets back to `start`. Handy for round-robin scheduling.
+  ///
+  /// This iterator covers (item_idx, &T)
+  pub fn iter_from(&self, start: usize) -> impl Iterator<Item = (usize, &T)> {
+    let wrapped = self.bitmap.iter_set().take_while(move |idx| *idx < start);
+    self.bitmap.iter_set_from(start).chain(wrapped).map(|idx| (idx, unsafe { self.data[idx].assume_init_ref() }) )
+  }
+
+  /// Walks the indices occupied in both this pond and `other`, like an ECS join over index-aligned columns.
+  /// The two bitmaps are intersected a word at a time, so slots only one side holds cost nothing.
+  ///
+  /// This iterator covers (item_idx, &T, &U)
+  pub fn zip_occupied<'a, U, N: Default>(&'a self, other: &'a Pond<U, N>) -> impl Iterator<Item = (usize, &'a T, &'a U)> {
+    let both = self.bitmap.words().iter().zip(other.bitmap.words()).map(|(ours, theirs)| ours & theirs);
+    // Safe because only slots occupied in both ponds are visited
+    set_bits(both).map(|idx| unsafe { (idx, self.data[idx].assume_init_ref(), other.data[idx].assume_init_ref()) })
+  }
+
+  /// Walks the occupied slots in order, yielding whatever `f` maps them to.
+  ///
+  /// Equivalent to `iter().filter_map(...)`, but skips free slots a bitmap word at a time
+  /// which is much faster on sparse ponds.
+  pub fn iter_filter_map<U, F: FnMut(usize, &T) -> Option<U>>(&self, mut f: F) -> impl Iterator<Item = U> {
+    set_bits(self.bitmap.words().iter().copied()).filter_map(move |idx| {
+      // Safe because set_bits only yields occupied slots
+      f(idx, unsafe { self.data[idx].assume_init_ref() })
+    })
+  }
+
+  /// Returns each run of consecutive occupied slots, in order.
+  pub fn occupied_ranges(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut search_from = 0;
+    std::iter::from_fn(move || {
+      let start = self.next_occupied(search_from)?;
+      search_from = self.next_free(start);
+      Some(start .. search_from)
+    })
+  }
+
+  /// Returns the occupied values as slices of at most `max` consecutive values, alongside the index each starts at.
+  /// Runs are broken by free slots, and runs longer than `max` are split. Handy for batched uploads.
+  ///
+  /// # Panics
+  /// If `max` is 0.
+  pub fn chunks_occupied(&self, max: usize) -> impl Iterator<Item = (usize, &[T])> {
+    assert!(max != 0, "chunks_occupied needs a max of at least 1");
+    self.occupied_ranges().flat_map(move |run| {
+      run.clone().step_by(max).map(move |start| {
+        let slots = &self.data[start .. run.end.min(start + max)];
+        // Every slot in the run is occupied, and MaybeUninit<T> is laid out just like T
+        (start, unsafe { std::slice::from_raw_parts(slots.as_ptr() as *const T, slots.len()) })
+      })
     })
   }
 
-  /// Returns an iterator over all valid items stored in this pond, in order.
+  /// Returns an iterator over all valid items stored in this pond, from least to most recently written.
+  /// Only ponds created with [Pond::with_recency] track this, others are iterated in index order.
+  ///
+  /// This iterator covers (item_idx, &T)
+  pub fn iter_by_recency(&self) -> impl Iterator<Item = (usize, &T)> {
+    let mut items: Vec<_> = self.iter().collect();
+    if let Some(recency) = &self.recency { items.sort_unstable_by_key(|(idx, _)| recency[*idx]) }
+    items.into_iter()
+  }
+
+  /// Returns an iterator over all valid items stored in this pond, in order. It can also be walked in reverse,
+  /// which is handy for algorithms that pull values from the back of the pond towards the front.
   ///
   /// This iterator covers (item_idx, &mut T)
-  pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
-    let bitmap = &self.bitmap;
-    self.data.iter_mut().enumerate().filter_map(|(idx, data)| {
-      // This is a safe call because we're iterating over avaliable slots already
-      if bitmap.is_set(idx) {
-        Some( (idx, unsafe { data.assume_init_mut() }) )
-      } else { None }
-    } ) 
+  pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+    unsafe { IterMut::new(self.bitmap.iter_set(), self.data.as_mut_ptr()) }
   }
 
 }
 
-use serde::{Serialize, Serializer, ser::SerializeSeq, Deserialize, Deserializer};
-impl<T> Serialize for Pond<T> where T: Serialize {
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, ser::SerializeSeq, Deserialize, Deserializer, de::Error};
+// Ponds are serialized as their length, the bitmap's occupancy words and then only the occupied values in index order,
+// so a free slot costs one bit. len() (trailing free slots included) and the layer count survive a round trip.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Pond")]
+struct StoredPond<B, V> {
+  len: usize,
+  bitmap: B,
+  values: V,
+}
+
+// The occupied values in index order, serialized straight from the pond rather than collected first
+#[cfg(feature = "serde")]
+struct Occupied<'a, T, M: Default>(&'a Pond<T, M>);
+#[cfg(feature = "serde")]
+impl<T: Serialize, M: Default> Serialize for Occupied<'_, T, M> {
   fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-    let mut seq = serializer.serialize_seq(Some(self.data.len()))?;
-    for idx in 0 .. self.data.len() { seq.serialize_element(&self.get(idx))?; }
+    let mut seq = serializer.serialize_seq(Some(self.0.count()))?;
+    for (_, value) in self.0.iter() { seq.serialize_element(value)?; }
     seq.end()
   }
 }
-impl<'de, T> Deserialize<'de> for Pond<T> where T: Deserialize<'de> {
+
+#[cfg(feature = "serde")]
+impl<T, M: Default> Serialize for Pond<T, M> where T: Serialize {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    StoredPond { len: self.len(), bitmap: &self.bitmap, values: Occupied(self) }.serialize(serializer)
+  }
+}
+#[cfg(feature = "serde")]
+impl<T: Serialize, M: Default> Pond<T, M> {
+  /// Returns how many bytes the pond takes up under bincode's default (fixed-width integer) encoding,
+  /// without serializing it, so a buffer can be sized up front. Other formats will differ.
+  ///
+  /// Only fails if one of the values' [Serialize] impls does.
+  pub fn serialized_size(&self) -> Result<usize, crate::SizeError> { crate::size::bincode_size(self) }
+}
+#[cfg(feature = "serde")]
+impl<'de, T, M: Default> Deserialize<'de> for Pond<T, M> where T: Deserialize<'de> {
   fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-    let data: Vec<Option<T>> = Vec::deserialize(deserializer)?;
-    let mut pool = Self::new();
-    pool.resize(data.len());
-    for (idx, pot_val) in data.into_iter().enumerate() {
-      if let Some(val) = pot_val { pool.write(idx, val); }
+    let StoredPond { len, bitmap, values } = StoredPond::<AcceleratedBitmap, Vec<T>>::deserialize(deserializer)?;
+    let mut pool = Self { bitmap: AcceleratedBitmap::new(bitmap.max_layers()), ..Self::empty() };
+    let words = bitmap.into_words();
+    // The values get written wherever the words say, so they have to agree with each other and with len
+    if words.len() != len.div_ceil(64) {
+      return Err(D::Error::custom(format!("{} occupancy words can't describe {len} slots", words.len())))
+    }
+    if !len.is_multiple_of(64) && words[words.len() - 1] & (!0 << (len % 64)) != 0 {
+      return Err(D::Error::custom(format!("occupancy words mark slots past the end of the {len} slots")))
+    }
+    let marked: usize = words.iter().map(|word| word.count_ones() as usize).sum();
+    if marked != values.len() {
+      return Err(D::Error::custom(format!("occupancy words mark {marked} slots but {} values were stored", values.len())))
     }
+    pool.resize(len);
+    for (idx, value) in set_bits(words.iter().copied()).zip(values) { pool.data[idx].write(value); }
+    pool.mark_reserved_words(words);
     Ok(pool)
   }
 }
@@ -0,0 +1,162 @@
+use serde::ser::{self, Serialize};
+use std::fmt;
+
+// Bincode (1.x, default options) writes lengths as u64s and enum variants as u32s
+const LEN: usize = size_of::<u64>();
+const VARIANT: usize = size_of::<u32>();
+
+/// Returns how many bytes `value` takes up in bincode's default fixed-width encoding, without serializing it.
+pub(crate) fn bincode_size<T: Serialize + ?Sized>(value: &T) -> Result<usize, SizeError> {
+  let mut counter = SizeCounter { size: 0 };
+  value.serialize(&mut counter)?;
+  Ok(counter.size)
+}
+
+/// Raised when a value's own [Serialize] impl fails while it's being measured.
+#[derive(Debug)]
+pub struct SizeError(String);
+impl fmt::Display for SizeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.0) }
+}
+impl std::error::Error for SizeError {}
+impl ser::Error for SizeError {
+  fn custom<E: fmt::Display>(msg: E) -> Self { Self(msg.to_string()) }
+}
+
+struct SizeCounter {
+  size: usize,
+}
+
+// Primitives are a fixed number of bytes
+macro_rules! fixed {
+  ($($method:ident: $ty:ty),*) => {$(
+    fn $method(self, _: $ty) -> Result<(), SizeError> {
+      self.size += size_of::<$ty>();
+      Ok(())
+    }
+  )*};
+}
+
+impl ser::Serializer for &mut SizeCounter {
+  type Ok = ();
+  type Error = SizeError;
+  type SerializeSeq = Self;
+  type SerializeTuple = Self;
+  type SerializeTupleStruct = Self;
+  type SerializeTupleVariant = Self;
+  type SerializeMap = Self;
+  type SerializeStruct = Self;
+  type SerializeStructVariant = Self;
+
+  fixed!(
+    serialize_bool: bool, serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+    serialize_i128: i128, serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64,
+    serialize_u128: u128, serialize_f32: f32, serialize_f64: f64
+  );
+
+  fn serialize_char(self, v: char) -> Result<(), SizeError> {
+    self.size += v.len_utf8();
+    Ok(())
+  }
+
+  fn serialize_str(self, v: &str) -> Result<(), SizeError> { self.serialize_bytes(v.as_bytes()) }
+
+  fn serialize_bytes(self, v: &[u8]) -> Result<(), SizeError> {
+    self.size += LEN + v.len();
+    Ok(())
+  }
+
+  fn serialize_none(self) -> Result<(), SizeError> {
+    self.size += 1;
+    Ok(())
+  }
+
+  fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SizeError> {
+    self.size += 1;
+    value.serialize(self)
+  }
+
+  fn serialize_unit(self) -> Result<(), SizeError> { Ok(()) }
+
+  fn serialize_unit_struct(self, _: &'static str) -> Result<(), SizeError> { Ok(()) }
+
+  fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<(), SizeError> {
+    self.size += VARIANT;
+    Ok(())
+  }
+
+  fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> Result<(), SizeError> {
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T: Serialize + ?Sized>(
+    self, _: &'static str, _: u32, _: &'static str, value: &T
+  ) -> Result<(), SizeError> {
+    self.size += VARIANT;
+    value.serialize(self)
+  }
+
+  fn serialize_seq(self, _: Option<usize>) -> Result<Self, SizeError> {
+    self.size += LEN;
+    Ok(self)
+  }
+
+  fn serialize_tuple(self, _: usize) -> Result<Self, SizeError> { Ok(self) }
+
+  fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, SizeError> { Ok(self) }
+
+  fn serialize_tuple_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self, SizeError> {
+    self.size += VARIANT;
+    Ok(self)
+  }
+
+  fn serialize_map(self, _: Option<usize>) -> Result<Self, SizeError> {
+    self.size += LEN;
+    Ok(self)
+  }
+
+  fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, SizeError> { Ok(self) }
+
+  fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self, SizeError> {
+    self.size += VARIANT;
+    Ok(self)
+  }
+}
+
+// Compound values are just the sum of their parts
+macro_rules! compound {
+  ($($trait:ident::$method:ident),*) => {$(
+    impl ser::$trait for &mut SizeCounter {
+      type Ok = ();
+      type Error = SizeError;
+      fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SizeError> { value.serialize(&mut **self) }
+      fn end(self) -> Result<(), SizeError> { Ok(()) }
+    }
+  )*};
+}
+compound!(
+  SerializeSeq::serialize_element, SerializeTuple::serialize_element,
+  SerializeTupleStruct::serialize_field, SerializeTupleVariant::serialize_field
+);
+
+macro_rules! fields {
+  ($($trait:ident),*) => {$(
+    impl ser::$trait for &mut SizeCounter {
+      type Ok = ();
+      type Error = SizeError;
+      fn serialize_field<T: Serialize + ?Sized>(&mut self, _: &'static str, value: &T) -> Result<(), SizeError> {
+        value.serialize(&mut **self)
+      }
+      fn end(self) -> Result<(), SizeError> { Ok(()) }
+    }
+  )*};
+}
+fields!(SerializeStruct, SerializeStructVariant);
+
+impl ser::SerializeMap for &mut SizeCounter {
+  type Ok = ();
+  type Error = SizeError;
+  fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SizeError> { key.serialize(&mut **self) }
+  fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SizeError> { value.serialize(&mut **self) }
+  fn end(self) -> Result<(), SizeError> { Ok(()) }
+}
@@ -0,0 +1,17 @@
+/// A frozen copy of a [crate::Pond]'s values and where they sit, see [crate::Pond::snapshot].
+///
+/// Metadata, recency, and handle counts aren't captured, restored slots start over like freshly written ones.
+#[derive(Clone, Debug)]
+pub struct Snapshot<T> {
+  pub(crate) len: usize,
+  pub(crate) words: Vec<u64>,
+  // The occupied values in index order
+  pub(crate) values: Vec<T>,
+}
+impl<T> Snapshot<T> {
+  /// The [crate::Pond::len] of the pond when the snapshot was taken.
+  pub fn len(&self) -> usize { self.len }
+
+  /// Returns true if the pond held no slots at all when the snapshot was taken.
+  pub fn is_empty(&self) -> bool { self.len == 0 }
+}
@@ -0,0 +1,68 @@
+use crate::bitmap::{AcceleratedBitmap, set_bits};
+
+/// A set of indices, backed by the same bitmap a [crate::Pond] uses to track occupancy.
+///
+/// Useful for tracking membership independently of a pond. Memory is proportional to the highest index inserted
+/// (a bit per index), and set operations work a word at a time.
+#[derive(Debug, Clone)]
+pub struct SparseSet {
+  bitmap: AcceleratedBitmap,
+}
+impl SparseSet {
+  /// Creates a new, empty [SparseSet]
+  pub fn new() -> Self { Self { bitmap: AcceleratedBitmap::new(3) } }
+
+  fn from_words(words: Vec<u64>) -> Self { Self { bitmap: AcceleratedBitmap::from_words(words, 3) } }
+
+  // Applies `op` to each pair of words, treating missing words as empty
+  fn combine(&self, other: &SparseSet, op: impl Fn(u64, u64) -> u64) -> Self {
+    let (ours, theirs) = (self.bitmap.words(), other.bitmap.words());
+    let word = |words: &[u64], idx: usize| words.get(idx).copied().unwrap_or(0);
+    let len = ours.len().max(theirs.len());
+    Self::from_words( (0 .. len).map(|idx| op(word(ours, idx), word(theirs, idx))).collect() )
+  }
+
+  /// Adds `idx` to the set, returning false if it was already present.
+  pub fn insert(&mut self, idx: usize) -> bool {
+    if self.contains(idx) { return false }
+    let capacity = self.bitmap.words().len() << 6;
+    // Grow a whole word at a time, there is no length to respect
+    if idx >= capacity { self.bitmap.resize((idx | 63) + 1) }
+    self.bitmap.set(idx, true);
+    true
+  }
+
+  /// Removes `idx` from the set, returning false if it wasn't present.
+  pub fn remove(&mut self, idx: usize) -> bool {
+    if !self.contains(idx) { return false }
+    self.bitmap.set(idx, false);
+    true
+  }
+
+  /// Checks whether `idx` is in the set.
+  pub fn contains(&self, idx: usize) -> bool {
+    idx < self.bitmap.words().len() << 6 && self.bitmap.is_set(idx)
+  }
+
+  /// Returns the number of indices in the set.
+  pub fn len(&self) -> usize { self.bitmap.count_set() }
+
+  /// Returns true if the set holds no indices.
+  pub fn is_empty(&self) -> bool { self.bitmap.words().iter().all(|word| *word == 0) }
+
+  /// Iterates over the indices in the set, in ascending order.
+  pub fn iter(&self) -> impl Iterator<Item = usize> + '_ { set_bits(self.bitmap.words().iter().copied()) }
+
+  /// Returns the indices in either set.
+  pub fn union(&self, other: &SparseSet) -> Self { self.combine(other, |a, b| a | b) }
+
+  /// Returns the indices in both sets.
+  pub fn intersection(&self, other: &SparseSet) -> Self { self.combine(other, |a, b| a & b) }
+
+  /// Returns the indices in `self` but not `other`.
+  pub fn difference(&self, other: &SparseSet) -> Self { self.combine(other, |a, b| a & !b) }
+}
+
+impl Default for SparseSet {
+  fn default() -> Self { Self::new() }
+}
@@ -0,0 +1,73 @@
+use crate::Pond;
+use std::ops::Deref;
+
+/// Groups inserts so they can be rolled back together, see [Pond::begin].
+///
+/// Dropping a transaction without calling [Transaction::commit] frees (and drops) everything inserted through it,
+/// leaving the rest of the pond untouched. Values evicted to make room for those inserts (see [Pond::with_eviction])
+/// are already gone, and aren't restored.
+pub struct Transaction<'a, T, M: Default = ()> {
+  pond: &'a mut Pond<T, M>,
+  inserted: Vec<usize>,
+}
+impl<'a, T, M: Default> Transaction<'a, T, M> {
+  pub(crate) fn new(pond: &'a mut Pond<T, M>) -> Self { Self { pond, inserted: Vec::new() } }
+
+  /// [Pond::insert]s `data`, remembering its index in case the transaction is rolled back.
+  #[must_use]
+  pub fn insert(&mut self, data: T) -> usize {
+    let idx = self.pond.insert(data);
+    self.inserted.push(idx);
+    idx
+  }
+
+  /// Keeps everything inserted during the transaction.
+  pub fn commit(mut self) { self.inserted.clear() }
+}
+
+impl<T, M: Default> Deref for Transaction<'_, T, M> {
+  type Target = Pond<T, M>;
+  fn deref(&self) -> &Pond<T, M> { self.pond }
+}
+
+impl<T, M: Default> Drop for Transaction<'_, T, M> {
+  fn drop(&mut self) {
+    for idx in self.inserted.drain(..).rev() { self.pond.free(idx); }
+  }
+}
+
+/// A slot set aside for a single value which may never arrive, see [Pond::reserve_slot].
+///
+/// The slot is claimed up front the same way [Pond::insert] claims one, so nothing else can be handed it,
+/// but it holds no value until [SlotToken::commit]. Aborting or dropping the token frees it again.
+/// The token borrows the pond so the empty slot can't be read in the meantime.
+pub struct SlotToken<'a, T, M: Default = ()> {
+  pond: &'a mut Pond<T, M>,
+  idx: usize,
+}
+impl<'a, T, M: Default> SlotToken<'a, T, M> {
+  pub(crate) fn new(pond: &'a mut Pond<T, M>) -> Self {
+    let idx = pond.reserve();
+    Self { pond, idx }
+  }
+
+  /// The index the value will be stored at once committed.
+  pub fn index(&self) -> usize { self.idx }
+
+  /// Stores `value` in the reserved slot, returning its index.
+  pub fn commit(self, value: T) -> usize {
+    // The slot is already marked occupied, so this initializes it like the rest
+    unsafe { self.pond.unsafe_data_mut()[self.idx].write(value); }
+    let idx = self.idx;
+    std::mem::forget(self);
+    idx
+  }
+
+  /// Gives the slot back without storing anything, the same as dropping the token.
+  pub fn abort(self) {}
+}
+
+impl<T, M: Default> Drop for SlotToken<'_, T, M> {
+  // Nothing was ever written, so the slot is freed without reading it
+  fn drop(&mut self) { self.pond.mark_free(self.idx) }
+}
@@ -1,4 +1,5 @@
-use lilypads::Pond;
+use lilypads::{Pond, SlotError, SparseSet};
+use std::collections::HashSet;
 
 #[test]
 fn insert() {
@@ -28,6 +29,59 @@ fn mut_get() {
   assert_eq!(*pool.get(idx).unwrap(), 13);
 }
 
+#[test]
+fn range_occupancy() {
+  let mut pool = Pond::new();
+  for i in 0 .. 100 { let _ = pool.insert(i); }
+  pool.free(70);
+
+  assert!(pool.is_range_occupied(0 .. 70));
+  assert!(!pool.is_range_occupied(60 .. 80));
+  assert!(pool.is_range_free(70 .. 71));
+  assert!(!pool.is_range_free(69 .. 71));
+  // Past the end is free space
+  assert!(pool.is_range_free(100 .. 200));
+  assert!(!pool.is_range_occupied(90 .. 101));
+  // Open ended ranges stop at the end rather than walking every index up to usize::MAX
+  assert!(!pool.is_range_free(71 .. usize::MAX));
+  assert!(pool.is_range_free(100 .. usize::MAX));
+  assert!(!pool.is_range_occupied(0 .. usize::MAX));
+}
+
+#[test]
+fn get_many() {
+  let mut pool = Pond::new();
+  let idx1 = pool.insert(1);
+  let idx2 = pool.insert(2);
+  let idx3 = pool.insert(3);
+  pool.free(idx2);
+
+  assert_eq!(pool.get_many([idx3, idx1]), Some([&3, &1]));
+  // Shared references are allowed to alias
+  assert_eq!(pool.get_many([idx1, idx1]), Some([&1, &1]));
+  // Any free or out of bounds index fails the whole request
+  assert_eq!(pool.get_many([idx1, idx2]), None);
+  assert_eq!(pool.get_many([idx1, 100]), None);
+}
+
+#[test]
+fn get_or_default() {
+  let mut pool = Pond::<Vec<u8>>::new();
+  let idx = pool.insert(vec![1]);
+
+  // Occupied slots are left alone
+  pool.get_or_default(idx).push(2);
+  assert_eq!(pool.get(idx).unwrap(), &vec![1, 2]);
+
+  // Free slots (even past the end) are defaulted
+  assert!(pool.get_or_default(5).is_empty());
+  pool.get_or_default(5).push(3);
+  assert_eq!(pool.get(5).unwrap(), &vec![3]);
+  assert_eq!(pool.count(), 2);
+
+  assert_eq!(pool.get_or_insert_with(2, || vec![4]), &vec![4]);
+}
+
 #[test] 
 fn free() {
   let mut pool = Pond::new();
@@ -41,6 +95,31 @@ fn free() {
   assert_eq!(pool.free(idx), None);
 }
 
+#[test]
+fn zeroing() {
+  let slot_bytes = |pool: &Pond<[u8; 16]>, idx: usize| {
+    let ptr = pool.unsafe_data()[idx].as_ptr() as *const u8;
+    unsafe { std::slice::from_raw_parts(ptr, 16).to_vec() }
+  };
+
+  let mut pool = Pond::new_zeroing();
+  let idx = pool.insert([0xAB; 16]);
+  let _ = pool.insert([0xCD; 16]);
+  assert_eq!(pool.free(idx), Some([0xAB; 16]));
+  assert_eq!(slot_bytes(&pool, idx), vec![0; 16]);
+  // Truncated slots are scrubbed too, they're still inside the allocation
+  pool.resize(1);
+  assert!(pool.capacity() >= 2);
+  let truncated = unsafe { std::slice::from_raw_parts(pool.unsafe_data().as_ptr().add(1) as *const u8, 16) };
+  assert_eq!(truncated, &[0; 16]);
+
+  // Regular ponds leave the bytes where they were
+  let mut pool = Pond::new();
+  let idx = pool.insert([0xAB; 16]);
+  pool.free(idx);
+  assert_eq!(slot_bytes(&pool, idx), vec![0xAB; 16]);
+}
+
 #[test]
 fn write() {
   let mut pool = Pond::new();
@@ -57,6 +136,148 @@ fn write() {
   assert_eq!(*pool.get(idx2).unwrap(), 29);
 }
 
+#[test]
+fn insert_near() {
+  let mut pool = Pond::new();
+  let indices: Vec<_> = (0..10).map(|i| pool.insert(i)).collect();
+  pool.free(indices[0]);
+  pool.free(indices[7]);
+
+  // Lands just after the cluster, rather than back at the front
+  assert_eq!(pool.insert_near(5, 100), 7);
+  // Nothing free after the hint, so it falls back to the first free slot rather than growing
+  assert_eq!(pool.insert_near(8, 101), 0);
+  // Hints past the end do the same, which grows the now full pond
+  assert_eq!(pool.insert_near(50, 102), 10);
+  assert_eq!(*pool.get(7).unwrap(), 100);
+  assert_eq!(*pool.get(0).unwrap(), 101);
+  assert_eq!(pool.len(), 11);
+
+  // The fallback is a plain insert, so it pops the free stack like one
+  let mut stacked = Pond::with_free_stack();
+  for val in 0 .. 10 { let _ = stacked.insert(val); }
+  stacked.free(0);
+  stacked.free(3);
+  assert_eq!(stacked.insert_near(5, 100), 3);
+  assert_eq!(stacked.next_index(), 0);
+}
+
+#[test]
+fn reserve_n() {
+  let mut pool = Pond::new();
+  let idx0 = pool.insert(0);
+  let _ = pool.insert(1);
+  let _ = pool.insert(2);
+  pool.free(idx0);
+
+  let count = pool.count();
+  let reserved = unsafe { pool.reserve_n(4) };
+  // Reuses the hole at the front, then grows
+  assert_eq!(reserved, vec![0, 3, 4, 5]);
+  assert_eq!(pool.count(), count + 4);
+  for idx in &reserved { assert!(pool.is_occupied(*idx)) }
+
+  let data = unsafe { pool.unsafe_data_mut() };
+  for idx in &reserved { data[*idx].write(*idx as i32 * 10); }
+  assert_eq!(*pool.get(5).unwrap(), 50);
+  assert_eq!(*pool.get(1).unwrap(), 1);
+}
+
+#[test]
+fn peek_free() {
+  let mut pool = Pond::new();
+  for i in 0 .. 100 { let _ = pool.insert(i); }
+  for idx in [3, 4, 50, 98] { pool.free(idx); }
+
+  let preview = pool.peek_free(7);
+  assert_eq!(preview, vec![3, 4, 50, 98, 100, 101, 102]);
+  // Previewing doesn't reserve anything
+  assert_eq!(pool.count(), 96);
+  let inserted: Vec<_> = (0 .. 7).map(|i| pool.insert(i)).collect();
+  assert_eq!(preview, inserted);
+}
+
+#[test]
+fn iter_filter_map() {
+  let mut pool = Pond::new();
+  pool.resize(1000);
+  for idx in [1, 64, 65, 300, 999] { pool.write(idx, idx * 2); }
+
+  let fused: Vec<_> = pool.iter_filter_map(|idx, val| (idx != 65).then_some(*val + 1)).collect();
+  let plain: Vec<_> = pool.iter().filter_map(|(idx, val)| (idx != 65).then_some(*val + 1)).collect();
+  assert_eq!(fused, vec![3, 129, 601, 1999]);
+  assert_eq!(fused, plain);
+}
+
+#[test]
+fn transaction() {
+  let mut pool = Pond::new();
+  let kept = pool.insert(0);
+  let hole = pool.insert(1);
+  let _ = pool.insert(2);
+  pool.free(hole);
+
+  // Rolled back on drop
+  let rolled_back = {
+    let mut transaction = pool.begin();
+    let indices = [transaction.insert(10), transaction.insert(11)];
+    assert_eq!(transaction.count(), 4);
+    indices
+  };
+  assert_eq!(rolled_back, [1, 3]);
+  assert_eq!(pool.count(), 2);
+  for idx in rolled_back { assert_eq!(pool.get(idx), None) }
+  assert_eq!(*pool.get(kept).unwrap(), 0);
+
+  // Kept on commit
+  let mut transaction = pool.begin();
+  let committed = transaction.insert(20);
+  transaction.commit();
+  assert_eq!(*pool.get(committed).unwrap(), 20);
+  assert_eq!(pool.count(), 3);
+}
+
+#[test]
+fn iter_by_recency() {
+  let mut pool = Pond::with_recency();
+  let a = pool.insert('a');
+  let b = pool.insert('b');
+  let c = pool.insert('c');
+  pool.free(a);
+  let d = pool.insert('d');
+  pool.write(b, 'e');
+  assert_eq!(a, d);
+
+  let order: Vec<_> = pool.iter_by_recency().collect();
+  assert_eq!(order, vec![(c, &'c'), (d, &'d'), (b, &'e')]);
+
+  // Recency follows values through defrag
+  pool.free(d);
+  let remap = pool.defrag();
+  let order: Vec<_> = pool.iter_by_recency().map(|(_, val)| *val).collect();
+  assert_eq!(order, vec!['c', 'e']);
+  assert_eq!(remap.get(&c), Some(&d));
+}
+
+#[test]
+fn set() {
+  let old = std::rc::Rc::new(1);
+  let mut pool = Pond::new();
+  let idx = pool.insert(old.clone());
+  assert_eq!(std::rc::Rc::strong_count(&old), 2);
+
+  // The previous occupant is dropped and the new one handed back
+  let new = pool.set(idx, std::rc::Rc::new(2));
+  assert_eq!(**new, 2);
+  *new = std::rc::Rc::new(3);
+  assert_eq!(std::rc::Rc::strong_count(&old), 1);
+  assert_eq!(**pool.get(idx).unwrap(), 3);
+
+  // Free and out of bounds slots are reserved
+  assert_eq!(**pool.set(10, std::rc::Rc::new(4)), 4);
+  assert_eq!(pool.count(), 2);
+}
+
 #[test]
 fn memory_reuse() {
   let mut pool = Pond::new();
@@ -72,6 +293,100 @@ fn memory_reuse() {
   assert_eq!(*pool.get(idx3).unwrap(), 3);
 }
 
+#[test]
+fn retain_map() {
+  let mut pool = Pond::new();
+  for i in 0 .. 10 { let _ = pool.insert(i.to_string()); }
+  pool.free(4);
+
+  pool.retain_map(|idx, val| (idx % 3 != 0).then(|| val + "!"));
+  assert_eq!(pool.count(), 5);
+  let remaining: Vec<_> = pool.iter().map(|(idx, val)| (idx, val.as_str())).collect();
+  assert_eq!(remaining, vec![(1, "1!"), (2, "2!"), (5, "5!"), (7, "7!"), (8, "8!")]);
+
+  // A panic part way through leaves the slot in question free, and everything else intact
+  let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    pool.retain_map(|idx, val| if idx == 5 { panic!() } else { Some(val) });
+  }));
+  assert!(result.is_err());
+  assert_eq!(pool.get(5), None);
+  assert_eq!(pool.get(7).unwrap(), "7!");
+  assert_eq!(pool.count(), 4);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn retain_map_keeps_recency() {
+  let mut pool = Pond::with_recency();
+  for idx in [3, 0, 2, 1] { pool.write(idx, idx); }
+  pool.retain_map(|idx, val| (idx != 2).then_some(val * 10));
+  let order: Vec<_> = pool.iter_by_recency().map(|(idx, val)| (idx, *val)).collect();
+  assert_eq!(order, vec![(3, 30), (0, 0), (1, 10)]);
+}
+
+#[test]
+fn retain_map_keeps_metadata() {
+  let mut pool = Pond::with_metadata::<u8>();
+  let a = pool.insert_tagged(5, 9);
+  let b = pool.insert_tagged(6, 7);
+  pool.retain_map(|_, val| Some(val + 1));
+  assert_eq!((pool.get(a), pool.meta(a)), (Some(&6), Some(&9)));
+  assert_eq!((pool.get(b), pool.meta(b)), (Some(&7), Some(&7)));
+}
+
+#[test]
+fn retain_map_keeps_handles() {
+  let mut pool = Pond::with_refcounting();
+  let idx = pool.insert(5);
+  pool.clone_handle(idx);
+  pool.clone_handle(idx);
+  assert_eq!(pool.handle_count(idx), Some(3));
+  pool.retain_map(|_, val| Some(val + 1));
+  assert_eq!(pool.handle_count(idx), Some(3));
+  assert_eq!(pool.free_handle(idx), None);
+  assert_eq!(pool.get(idx), Some(&6));
+}
+
+#[test]
+fn drain_into() {
+  let mut pool = Pond::new();
+  let mut out = vec![-1];
+  pool.write(5, 5);
+  pool.write(1, 1);
+  pool.write(70, 70);
+
+  pool.drain_into(&mut out);
+  // Appends rather than overwriting
+  assert_eq!(out, vec![-1, 1, 5, 70]);
+  assert!(pool.is_empty());
+  assert_eq!(pool.count(), 0);
+  assert_eq!(pool.next_index(), 0);
+}
+
+#[test]
+fn iter_mut_rev() {
+  let mut pool = Pond::new();
+  for i in 0 .. 6 { let _ = pool.insert(i); }
+  pool.free(2);
+  pool.free(5);
+
+  // Each value becomes the running count of slots visited from the back
+  let mut visited = Vec::new();
+  for (step, (idx, val)) in pool.iter_mut().rev().enumerate() {
+    visited.push(idx);
+    *val = step * 10;
+  }
+  assert_eq!(visited, vec![4, 3, 1, 0]);
+  let values: Vec<_> = pool.iter().map(|(_, val)| *val).collect();
+  assert_eq!(values, vec![30, 20, 10, 0]);
+
+  // Both ends can be consumed from the same iterator
+  let mut iter = pool.iter_mut();
+  assert_eq!(iter.next().map(|(idx, _)| idx), Some(0));
+  assert_eq!(iter.next_back().map(|(idx, _)| idx), Some(4));
+  assert_eq!(iter.count(), 2);
+}
+
 #[test]
 fn defrag() {
   let mut pool = Pond::new();
@@ -91,6 +406,56 @@ fn defrag() {
   assert_eq!(pool.next_index(), 3);
 }
 
+#[test]
+fn metadata() {
+  let mut pool = Pond::with_metadata::<u8>();
+  let a = pool.insert_tagged("a", 1);
+  let b = pool.insert_tagged("b", 2);
+  let c = pool.insert("c");
+  let d = pool.insert_tagged("d", 4);
+  // Untagged inserts get the default
+  assert_eq!(pool.meta(c), Some(&0));
+  *pool.meta_mut(c).unwrap() = 3;
+
+  pool.free(a);
+  assert_eq!(pool.meta(a), None);
+  // Metadata moves along with its value
+  let remap = pool.defrag();
+  assert_eq!(remap.get(&d), Some(&a));
+  assert_eq!(pool.get(a), Some(&"d"));
+  assert_eq!(pool.meta(a), Some(&4));
+  assert_eq!(pool.meta(b), Some(&2));
+  assert_eq!(pool.meta(c), Some(&3));
+  assert_eq!(pool.meta(d), None);
+
+  // Writing a new value resets its metadata
+  pool.write(b, "e");
+  assert_eq!(pool.meta(b), Some(&0));
+}
+
+#[test]
+fn defrag_range() {
+  let mut pool = Pond::new();
+  for i in 0 .. 20 { let _ = pool.insert(i); }
+  for idx in [1, 3, 6, 8, 12, 15] { pool.free(idx); }
+  let before = pool.occupancy_snapshot();
+
+  let remap = pool.defrag_range(5 .. 12);
+  // 6 and 8 are filled from 11 and 10, 9 is left where it is
+  assert_eq!(remap.len(), 2);
+  assert_eq!(remap.get(&11), Some(&6));
+  assert_eq!(remap.get(&10), Some(&8));
+  assert!(pool.is_range_occupied(5 .. 10));
+  assert!(pool.is_range_free(10 .. 12));
+  assert_eq!(*pool.get(6).unwrap(), 11);
+
+  // Everything outside of the range is untouched
+  let (added, removed) = before.diff(&pool.occupancy_snapshot());
+  assert!(added.iter().chain(&removed).all(|idx| (5 .. 12).contains(idx)));
+  assert!(pool.is_occupied(0) && !pool.is_occupied(1) && !pool.is_occupied(15));
+  assert_eq!(pool.validate(), Ok(()));
+}
+
 #[test]
 fn trim_normal() {
   let mut pool = Pond::new();
@@ -105,8 +470,8 @@ fn trim_normal() {
   for (old, new) in remapped.iter() { indices[*old] = *new }
 
   // Verify memory state after trim
-  assert!(matches!(pool.get(2), Some(_)));
-  assert!(matches!(pool.get(3), None));
+  assert!(pool.get(2).is_some());
+  assert!(pool.get(3).is_none());
 
   // Verify insertator state after trim
   assert_eq!(pool.next_index(), 3);
@@ -162,9 +527,120 @@ fn trim_free() {
   assert_eq!(pool.next_index(), 0);
 }
 
+#[test]
+fn try_grow() {
+  let mut pool = Pond::<u32>::new();
+  assert!(pool.try_grow(100));
+  pool.resize(50);
+  // There's still room for 100 slots
+  assert!(!pool.try_grow(80));
+  assert_eq!(pool.len(), 80);
+  assert!(!pool.try_grow(10));
+  assert_eq!(pool.len(), 80);
+  assert!(pool.try_grow(10_000));
+  assert_eq!(pool.len(), 10_000);
+}
+
+#[test]
+fn shrink_to() {
+  let mut pool = Pond::new();
+  pool.resize(1000);
+  pool.write(10, 10);
+  pool.write(300, 300);
+
+  // Occupied slots are never truncated
+  pool.shrink_to(50);
+  assert_eq!(pool.len(), 301);
+  assert!(pool.capacity() >= 301 && pool.capacity() < 1000);
+  assert_eq!(*pool.get(300).unwrap(), 300);
+
+  // The floor keeps headroom
+  pool.free(300);
+  pool.shrink_to(50);
+  assert_eq!(pool.len(), 50);
+  assert!(pool.capacity() >= 50 && pool.capacity() < 301);
+  assert_eq!(*pool.get(10).unwrap(), 10);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn try_reserve() {
+  let mut pool = Pond::<u64>::new();
+  let _ = pool.insert(1);
+  assert!(pool.try_reserve(1000).is_ok());
+  assert!(pool.capacity() >= 1001);
+  assert_eq!(pool.len(), 1);
+
+  // Far too large to ever succeed, but it shouldn't abort
+  assert!(pool.try_reserve(usize::MAX / 2).is_err());
+  assert_eq!(*pool.get(0).unwrap(), 1);
+  let _ = pool.insert(2);
+  assert_eq!(pool.count(), 2);
+}
+
+#[test]
+fn memory_usage() {
+  let mut pool = Pond::<u64>::new();
+  assert_eq!(pool.memory_usage(), 0);
+  let indices: Vec<_> = (0 .. 100_000).map(|i| pool.insert(i)).collect();
+  let grown = pool.memory_usage();
+  assert!(grown >= 100_000 * 8);
+
+  for idx in &indices[10 ..] { pool.free(*idx); }
+  _ = pool.trim();
+  // Trimming shortens the pond but keeps the data allocation around
+  assert!(pool.memory_usage() >= 100_000 * 8);
+  pool.shrink_to_fit();
+  assert!(pool.memory_usage() < 200);
+  assert_eq!(*pool.get(9).unwrap(), 9);
+}
+
+#[test]
+fn zero_sized() {
+  let mut pool = Pond::new();
+  let indices: Vec<_> = (0 .. 100_000).map(|_| pool.insert(())).collect();
+  assert_eq!(pool.get(indices[500]), Some(&()));
+  assert_eq!(pool.free(indices[500]), Some(()));
+  assert_eq!(pool.get(indices[500]), None);
+  assert_eq!(pool.insert(()), indices[500]);
+  assert_eq!(pool.count(), 100_000);
+  // Only the bitmap takes up space, about a bit per slot
+  assert!(pool.memory_usage() < 100_000 / 4);
+}
+
+#[test]
+fn validate() {
+  let mut pool = Pond::new();
+  assert_eq!(pool.validate(), Ok(()));
+  for i in 0 .. 5000 { let _ = pool.insert(i); }
+  for idx in (0 .. 5000).step_by(7) { pool.free(idx); }
+  pool.write(9000, 1);
+  assert_eq!(pool.validate(), Ok(()));
+  _ = pool.trim();
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip_trailing_free() {
+  let mut pool = Pond::new();
+  pool.resize(10);
+  pool.write(0, 7);
+  pool.write(4, 9);
+
+  let json = serde_json::to_string(&pool).unwrap();
+  let restored: Pond<i32> = serde_json::from_str(&json).unwrap();
+
+  // Trailing free slots shouldn't be collapsed away
+  assert_eq!(restored.len(), 10);
+  for idx in 0 .. 10 { assert_eq!(restored.get(idx), pool.get(idx)) }
+  assert_eq!(restored.next_index(), 1);
+  assert_eq!(restored.validate(), Ok(()));
+}
+
 #[test]
 fn stress() {
-  const N: u32 = 1_000_000_0;
+  const N: u32 = 10_000_000;
   let mut pool = Pond::new();
   pool.resize(N as usize);
 
@@ -172,6 +648,29 @@ fn stress() {
   for i in 0..N { let _ = pool.insert(i); }
 }
 
+#[test]
+fn insert_growth_is_geometric() {
+  let mut pool = Pond::<u64>::new();
+  let mut reallocations = 0;
+  let mut memory = pool.memory_usage();
+  for i in 0 .. 100_000 {
+    let _ = pool.insert(i);
+    if pool.memory_usage() != memory { reallocations += 1; memory = pool.memory_usage(); }
+  }
+  // Counts bitmap growth too, which also doubles
+  assert!(reallocations < 40, "{reallocations} reallocations");
+}
+
+#[test]
+fn insert_without_resize() {
+  // No manual resize, each insert appends a slot
+  let mut pool = Pond::new();
+  for i in 0 .. 1_000_000 { assert_eq!(pool.insert(i), i); }
+  assert_eq!(pool.len(), 1_000_000);
+  assert_eq!(pool.count(), 1_000_000);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
 #[test]
 fn bitmap_resize_boundary() {
   let mut pool = Pond::new();
@@ -182,3 +681,1273 @@ fn bitmap_resize_boundary() {
 }
 
 
+
+#[test]
+fn occupancy_snapshot_diff() {
+  let mut pool = Pond::new();
+  let idx0 = pool.insert(0);
+  let idx1 = pool.insert(1);
+  let before = pool.occupancy_snapshot();
+
+  pool.free(idx0);
+  let idx2 = pool.insert(2);
+  pool.free(idx2);
+  pool.write(100, 3);
+  pool.free(idx1);
+  let _ = pool.insert(4);
+  let after = pool.occupancy_snapshot();
+
+  // Slot 0 was freed and refilled, so it shouldn't show up at all
+  assert_eq!(before.diff(&after), (vec![100], vec![1]));
+  assert_eq!(after.diff(&before), (vec![1], vec![100]));
+  assert_eq!(after.diff(&after), (vec![], vec![]));
+}
+
+#[test]
+fn sparse_set_algebra() {
+  // Small xorshift so the sets are scattered but reproducible
+  let mut seed = 0x2545F4914F6CDD1D_u64;
+  let mut next = || { seed ^= seed << 13; seed ^= seed >> 7; seed ^= seed << 17; seed as usize };
+
+  let (mut a, mut b) = (SparseSet::new(), SparseSet::new());
+  let (mut oracle_a, mut oracle_b) = (HashSet::new(), HashSet::new());
+  for _ in 0 .. 2000 {
+    let idx = next() % 5000;
+    assert_eq!(a.insert(idx), oracle_a.insert(idx));
+    let idx = next() % 3000;
+    assert_eq!(b.insert(idx), oracle_b.insert(idx));
+    let idx = next() % 5000;
+    if next() % 4 == 0 { assert_eq!(a.remove(idx), oracle_a.remove(&idx)) }
+  }
+  assert_eq!(a.len(), oracle_a.len());
+  assert!(a.contains(*oracle_a.iter().next().unwrap()));
+  assert!(!a.contains(100_000));
+
+  let sorted = |set: HashSet<usize>| { let mut v: Vec<_> = set.into_iter().collect(); v.sort(); v };
+  assert_eq!(a.iter().collect::<Vec<_>>(), sorted(oracle_a.clone()));
+  assert_eq!(a.union(&b).iter().collect::<Vec<_>>(), sorted(&oracle_a | &oracle_b));
+  assert_eq!(a.intersection(&b).iter().collect::<Vec<_>>(), sorted(&oracle_a & &oracle_b));
+  assert_eq!(a.difference(&b).iter().collect::<Vec<_>>(), sorted(&oracle_a - &oracle_b));
+  assert_eq!(b.difference(&a).iter().collect::<Vec<_>>(), sorted(&oracle_b - &oracle_a));
+  assert!(a.difference(&a).is_empty());
+}
+
+#[test]
+fn nearest_occupied() {
+  let mut pool = Pond::new();
+  assert_eq!(pool.nearest_occupied(5), None);
+  for idx in [10, 20, 300] { pool.write(idx, idx); }
+
+  assert_eq!(pool.nearest_occupied(10), Some(10));
+  assert_eq!(pool.nearest_occupied(0), Some(10));
+  assert_eq!(pool.nearest_occupied(14), Some(10));
+  // Equally far from 10 and 20
+  assert_eq!(pool.nearest_occupied(15), Some(10));
+  assert_eq!(pool.nearest_occupied(16), Some(20));
+  assert_eq!(pool.nearest_occupied(250), Some(300));
+  assert_eq!(pool.nearest_occupied(10_000), Some(300));
+}
+
+#[test]
+fn count_in_range() {
+  let mut pool = Pond::new();
+  for idx in 0 .. 300 { let _ = pool.insert(idx); }
+  // Leaves every third slot occupied
+  for idx in (0 .. 300).filter(|idx| idx % 3 != 0) { pool.free(idx); }
+
+  assert_eq!(pool.count_in_range(0 .. 300), 100);
+  assert_eq!(pool.count_in_range(60 .. 130), (60 .. 130).filter(|idx| idx % 3 == 0).count());
+  assert_eq!(pool.count_in_range(63 .. 65), 1);
+  assert_eq!(pool.count_in_range(250 .. 1000), (250 .. 300).filter(|idx| idx % 3 == 0).count());
+  assert_eq!(pool.count_in_range(40 .. 40), 0);
+  assert_eq!(pool.count_in_range(0 .. usize::MAX), 100);
+  assert_eq!(pool.count_in_range(298 .. usize::MAX), 0);
+}
+
+#[test]
+fn option_vec_round_trip() {
+  let values = vec![None, Some(String::from("a")), None, None, Some(String::from("b")), None];
+  let pool = Pond::from_option_vec(values.clone());
+  assert_eq!(pool.len(), 6);
+  assert_eq!(pool.count(), 2);
+  assert_eq!(pool.get(4).map(String::as_str), Some("b"));
+  assert_eq!(pool.into_option_vec(), values);
+}
+
+#[test]
+fn cursor() {
+  let mut pool = Pond::new();
+  for idx in 0 .. 10 { let _ = pool.insert(idx); }
+
+  let mut cursor = pool.cursor();
+  let mut keep = false;
+  while let Some((_, val)) = cursor.next() {
+    *val *= 10;
+    if !keep { assert!(cursor.remove_current().is_some()); }
+    keep = !keep;
+  }
+  // Inserted past everything visited so far, so the cursor picks it up again
+  cursor.insert_after(1000);
+  assert_eq!(cursor.next().map(|(_, val)| *val), Some(1000));
+  assert!(cursor.next().is_none());
+
+  let left: Vec<_> = pool.iter().map(|(idx, val)| (idx, *val)).collect();
+  assert_eq!(left, vec![(1, 10), (3, 30), (5, 50), (7, 70), (9, 90), (10, 1000)]);
+}
+
+#[test]
+fn eviction_cap_everywhere() {
+  let mut cache = Pond::with_eviction(4);
+  for val in 0 .. 4 { let _ = cache.insert(val); }
+  // Full up to the cap, so each of these evicts the oldest value rather than growing
+  assert_eq!(cache.insert_near(2, 10), 0);
+  assert_eq!(cache.reserve_slot().commit(11), 1);
+  // Slot 2 is the oldest of the aligned ones, 0 having just been replaced
+  assert_eq!(cache.insert_aligned(2, 12), 2);
+  let mut cursor = cache.cursor();
+  cursor.next();
+  assert_eq!(cursor.insert_after(13), 3);
+  assert_eq!(cache.len(), 4);
+  assert_eq!(cache.iter().map(|(_, val)| *val).collect::<Vec<_>>(), vec![10, 11, 12, 13]);
+
+  // Slots written past the cap by hand aren't reused once freed
+  let mut stacked = Pond::builder().max_capacity(2).free_stack().build();
+  stacked.write(5, 'x');
+  stacked.free(5);
+  let _ = stacked.insert('a');
+  assert_eq!(stacked.insert('b'), 1);
+  assert_eq!(stacked.insert('c'), 0);
+}
+
+#[test]
+#[should_panic(expected = "past the pond's eviction cap")]
+fn eviction_cap_explicit_slot() {
+  let mut cache = Pond::with_eviction(4);
+  cache.get_or_insert_at(4, 'a');
+}
+
+#[test]
+#[should_panic(expected = "eviction cap of 0")]
+fn eviction_cap_zero() {
+  let _: Pond<char> = Pond::with_eviction(0);
+}
+
+#[test]
+fn slot_token() {
+  let mut pool = Pond::new();
+  let _ = pool.insert(1);
+
+  let token = pool.reserve_slot();
+  let reserved = token.index();
+  token.abort();
+  assert!(!pool.is_occupied(reserved));
+  assert_eq!(pool.next_index(), reserved);
+
+  // A failed build just drops the token
+  let built: Result<i32, ()> = Err(());
+  {
+    let token = pool.reserve_slot();
+    if let Ok(val) = built { token.commit(val); }
+  }
+  assert!(!pool.is_occupied(reserved));
+
+  let idx = pool.reserve_slot().commit(2);
+  assert_eq!(idx, reserved);
+  assert_eq!(pool.get(idx), Some(&2));
+
+  // The slot is claimed for real, so a token past the end grows the pond just like an insert
+  let token = pool.reserve_slot();
+  assert_eq!(token.index(), 2);
+  drop(token);
+  assert_eq!(pool.len(), 3);
+  assert_eq!(pool.count(), 2);
+  assert_eq!(pool.next_index(), 2);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn shrink_to_occupancy() {
+  let mut pool = Pond::new();
+  for idx in 0 .. 200 { let _ = pool.insert(idx); }
+  for idx in (100 .. 200).chain([5, 70]) { pool.free(idx); }
+
+  pool.shrink_to_occupancy();
+  assert_eq!(pool.len(), 100);
+  assert!(!pool.is_occupied(5) && !pool.is_occupied(70));
+  assert_eq!(pool.get(99), Some(&99));
+  assert_eq!(pool.validate(), Ok(()));
+
+  for idx in 0 .. 100 { pool.free(idx); }
+  pool.shrink_to_occupancy();
+  assert!(pool.is_empty());
+}
+
+#[test]
+fn prefault() {
+  let mut pool = Pond::new();
+  for idx in 0 .. 5000u64 { let _ = pool.insert([idx; 4]); }
+  for idx in (0 .. 5000).step_by(3) { pool.free(idx); }
+  let _ = pool.try_reserve(20_000);
+  pool.prefault();
+
+  for idx in 0 .. 5000 {
+    let expected = [idx as u64; 4];
+    assert_eq!(pool.get(idx), if idx % 3 == 0 { None } else { Some(&expected) });
+  }
+  assert!(pool.capacity() >= 25_000);
+}
+
+#[test]
+fn eviction() {
+  let mut cache = Pond::with_eviction(3);
+  for val in 0 .. 3 { assert_eq!(cache.insert_evicting(val).1, None); }
+
+  let (idx, evicted) = cache.insert_evicting(3);
+  assert_eq!((idx, evicted), (0, Some(0)));
+  assert_eq!(cache.insert_evicting(4), (1, Some(1)));
+  // Freeing makes room without evicting
+  cache.free(0);
+  assert_eq!(cache.insert_evicting(5), (0, None));
+  assert_eq!(cache.insert_evicting(6), (2, Some(2)));
+  let _ = cache.insert(7);
+
+  assert_eq!(cache.count(), 3);
+  let mut left: Vec<_> = cache.iter().map(|(_, val)| *val).collect();
+  left.sort();
+  assert_eq!(left, vec![5, 6, 7]);
+}
+
+#[test]
+fn occupancy_words() {
+  let mut pool = Pond::new();
+  for idx in [0, 1, 63, 64, 100, 191] { pool.write(idx, ()); }
+  let words = pool.occupancy_words();
+  assert_eq!(words.len(), 3);
+  for idx in 0 .. 192 {
+    assert_eq!(words[idx / 64] >> (idx % 64) & 1 == 1, pool.is_occupied(idx));
+  }
+}
+
+#[test]
+fn defrag_into() {
+  let mut pool = Pond::new();
+  let mut remap = std::collections::HashMap::new();
+  for round in 0 .. 3 {
+    for val in 0 .. 20 { pool.write(val + 20, val); }
+    pool.defrag_into(&mut remap);
+    // Nothing from the previous round lingers
+    assert_eq!(remap.len(), 20, "round {round}");
+    for (from, to) in &remap { assert_eq!(pool.get(*to), Some(&(*from - 20))); }
+    for val in 0 .. 20 { pool.free(val); }
+  }
+}
+
+#[test]
+fn index_two_mut() {
+  let mut pool = Pond::new();
+  let a = pool.insert(1);
+  let b = pool.insert(2);
+  let (x, y) = pool.index_two_mut(a, b);
+  std::mem::swap(x, y);
+  assert_eq!((pool.get(a), pool.get(b)), (Some(&2), Some(&1)));
+
+  assert!(pool.get_pair_mut(a, a).is_none());
+  assert!(pool.get_pair_mut(a, 7).is_none());
+}
+
+#[test]
+#[should_panic(expected = "same index")]
+fn index_two_mut_aliasing() {
+  let mut pool = Pond::new();
+  let a = pool.insert(1);
+  pool.index_two_mut(a, a);
+}
+
+#[test]
+fn high_water_mark() {
+  let mut pool = Pond::new();
+  for val in 0 .. 10 { let _ = pool.insert(val); }
+  for idx in 0 .. 6 { pool.free(idx); }
+  assert_eq!(pool.high_water_mark(), 10);
+  // Overwriting doesn't add to the count
+  pool.write(8, 0);
+  for val in 0 .. 5 { let _ = pool.insert(val); }
+  assert_eq!((pool.count(), pool.high_water_mark()), (9, 10));
+  pool.write(30, 0);
+  pool.write(31, 0);
+  assert_eq!(pool.high_water_mark(), 11);
+
+  pool.resize(10);
+  pool.reset_high_water_mark();
+  assert_eq!(pool.high_water_mark(), 9);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn swap_with_last_occupied() {
+  let mut pool = Pond::new();
+  for val in 0 .. 10 { let _ = pool.insert(val); }
+  pool.free(2);
+  pool.free(9);
+
+  // Filling a hole
+  assert_eq!(pool.swap_with_last_occupied(2), Some(8));
+  assert_eq!(pool.get(2), Some(&8));
+  assert!(!pool.is_occupied(8));
+  // Swapping two values
+  assert_eq!(pool.swap_with_last_occupied(0), Some(7));
+  assert_eq!((pool.get(0), pool.get(7)), (Some(&7), Some(&0)));
+
+  assert_eq!(pool.swap_with_last_occupied(7), None);
+  assert_eq!(pool.swap_with_last_occupied(20), None);
+  pool.shrink_to_occupancy();
+  assert_eq!(pool.len(), 8);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn reserve_for_occupancy() {
+  let mut pool = Pond::new();
+  pool.reserve_for_occupancy(10_000);
+  assert_eq!(pool.len(), 10_000);
+  let memory = pool.memory_usage();
+  for val in 0 .. 10_000 { let _ = pool.insert(val); }
+  assert_eq!(pool.memory_usage(), memory);
+  assert_eq!(pool.len(), 10_000);
+
+  // Already long enough
+  pool.reserve_for_occupancy(50);
+  assert_eq!(pool.len(), 10_000);
+}
+
+#[test]
+fn for_each_with_neighbors() {
+  // A 3x3 grid with a hole in the middle
+  let mut grid = Pond::new();
+  for val in 0 .. 9 { let _ = grid.insert(val as f32); }
+  grid.free(4);
+
+  let mut seen = Vec::new();
+  grid.for_each_with_neighbors(&[-3, -1, 0, 1, 3], |idx, val, neighbors| {
+    seen.push( (idx, neighbors.iter().map(|n| n.copied()).collect::<Vec<_>>()) );
+    let present: Vec<f32> = neighbors.iter().flatten().map(|n| **n).collect();
+    *val = (*val + present.iter().sum::<f32>()) / (present.len() + 1) as f32;
+  });
+
+  assert_eq!(seen[0], (0, vec![None, None, None, Some(1.0), Some(3.0)]));
+  // 0 was blurred before 1 got to it, and the hole at 4 is skipped
+  assert_eq!(seen[1], (1, vec![None, Some(4.0 / 3.0), None, Some(2.0), None]));
+  assert_eq!(seen.len(), 8);
+  assert_eq!(grid.get(0), Some(&(4.0 / 3.0)));
+  assert!(!grid.is_occupied(4));
+}
+
+#[test]
+fn checked_access() {
+  let mut pool = Pond::new();
+  let idx = pool.insert(5);
+  pool.write(3, 6);
+
+  assert_eq!(pool.get_checked(idx), Ok(&5));
+  assert_eq!(pool.get_checked(1), Err(SlotError::Vacant { idx: 1 }));
+  assert_eq!(pool.get_checked(10), Err(SlotError::OutOfBounds { idx: 10, len: 4 }));
+  assert_eq!(pool.free_checked(3), Ok(6));
+  assert_eq!(pool.free_checked(3), Err(SlotError::Vacant { idx: 3 }));
+  assert_eq!(pool.free_checked(4).unwrap_err().to_string(), "slot 4 is out of bounds for a pond of length 4");
+}
+
+#[test]
+fn retain_compact() {
+  let mut pool = Pond::new();
+  for val in 0 .. 20 { let _ = pool.insert(val); }
+  for idx in [1, 2, 9] { pool.free(idx); }
+
+  let remap = pool.retain_compact(|_, val| val % 3 != 0);
+  let survivors: Vec<_> = pool.iter().map(|(idx, val)| (idx, *val)).collect();
+  assert_eq!(survivors, vec![(0, 4), (1, 5), (2, 7), (3, 8), (4, 10), (5, 11), (6, 13), (7, 14), (8, 16), (9, 17), (10, 19)]);
+  for (old, new) in &remap { assert_eq!(pool.get(*new), Some(old)); }
+  assert_eq!(remap.len(), 11);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn new_aligned_to() {
+  let mut positions = Pond::new();
+  for val in 0 .. 10 { let _ = positions.insert(val as f32); }
+  let mut velocities = Pond::new_aligned_to(&positions);
+  assert_eq!(velocities.len(), positions.len());
+  assert_eq!(velocities.count(), 0);
+  for (idx, pos) in positions.iter() { velocities.write(idx, -*pos); }
+
+  for idx in [1, 4, 5] {
+    positions.free(idx);
+    velocities.free(idx);
+  }
+  let remap = positions.defrag();
+  for (old, new) in remap {
+    let val = velocities.free(old).unwrap();
+    velocities.write(new, val);
+  }
+  for (idx, pos) in positions.iter() { assert_eq!(velocities.get(idx), Some(&-*pos)); }
+  assert_eq!(velocities.count(), positions.count());
+}
+
+#[test]
+fn insert_aligned() {
+  let mut pool = Pond::new();
+  for val in 0 .. 20 { let _ = pool.insert(val); }
+  for idx in [1, 2, 3, 9, 12] { pool.free(idx); }
+
+  assert_eq!(pool.insert_aligned(4, 100), 12);
+  // 1 through 3 are free, but none of them are aligned
+  let indices: Vec<_> = (0 .. 3).map(|val| pool.insert_aligned(4, val)).collect();
+  assert_eq!(indices, vec![20, 24, 28]);
+  assert!(indices.iter().all(|idx| idx % 4 == 0));
+  assert_eq!(pool.insert_aligned(3, 0), 3);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn drain_filter() {
+  let mut pool = Pond::new();
+  for val in 0 .. 10 { let _ = pool.insert(val * 10); }
+  pool.free(4);
+
+  let drained = pool.drain_filter(|idx, val| idx < 6 && val % 20 == 0);
+  assert_eq!(drained, vec![(0, 0), (2, 20)]);
+  assert_eq!(pool.count(), 7);
+  assert!(!pool.is_occupied(0) && !pool.is_occupied(2));
+  assert_eq!(pool.get(6), Some(&60));
+}
+
+#[test]
+fn trait_objects() {
+  trait Shape { fn area(&self) -> f32; fn scale(&mut self, by: f32); }
+  struct Square(f32);
+  struct Circle(f32);
+  impl Shape for Square {
+    fn area(&self) -> f32 { self.0 * self.0 }
+    fn scale(&mut self, by: f32) { self.0 *= by }
+  }
+  impl Shape for Circle {
+    fn area(&self) -> f32 { 3.0 * self.0 * self.0 }
+    fn scale(&mut self, by: f32) { self.0 *= by }
+  }
+
+  let mut shapes: Pond<Box<dyn Shape>> = Pond::new();
+  let square = shapes.insert(Box::new(Square(2.0)));
+  let circle = shapes.insert(Box::new(Circle(1.0)));
+  shapes.get_dyn_mut(circle).unwrap().scale(2.0);
+
+  assert_eq!(shapes.get_dyn(square).map(|shape| shape.area()), Some(4.0));
+  assert_eq!(shapes.get_dyn(circle).map(|shape| shape.area()), Some(12.0));
+  let total: f32 = shapes.iter().map(|(_, shape)| shape.area()).sum();
+  assert_eq!(total, 16.0);
+}
+
+#[test]
+fn try_resize() {
+  let mut pool = Pond::new();
+  for val in 0 .. 10 { let _ = pool.insert(val); }
+  for idx in 5 .. 10 { pool.free(idx); }
+  pool.write(20, 20);
+
+  assert_eq!(pool.try_resize(15), Err(20));
+  assert_eq!(pool.len(), 21);
+  pool.free(20);
+  assert_eq!(pool.try_resize(5), Ok(()));
+  assert_eq!(pool.len(), 5);
+  assert_eq!(pool.try_resize(4), Err(4));
+  assert_eq!(pool.try_resize(50), Ok(()));
+  assert_eq!(pool.count(), 5);
+}
+
+#[test]
+fn get_or_insert_at() {
+  let mut pool = Pond::new();
+  // Miss, past the end
+  *pool.get_or_insert_at(4, String::from("a")) += "b";
+  assert_eq!(pool.get(4).map(String::as_str), Some("ab"));
+  // Hit, the new value is thrown away
+  assert_eq!(pool.get_or_insert_at(4, String::from("c")), "ab");
+  assert_eq!(pool.count(), 1);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_sparse() {
+  let mut pool: Pond<u64> = Pond::with_layers(2);
+  pool.resize(10_000);
+  for idx in (0 .. 10_000).step_by(1000) { pool.write(idx, idx as u64); }
+
+  // len, then the bitmap's words and layer count, then just the 10 values, each sequence prefixed by its length
+  let bytes = bincode::serialize(&pool).unwrap();
+  assert_eq!(bytes.len(), 8 + (8 + 157 * 8 + 8) + (8 + 10 * 8));
+  let restored: Pond<u64> = bincode::deserialize(&bytes).unwrap();
+  assert_eq!(restored.len(), 10_000);
+  assert!(restored.iter().eq(pool.iter()));
+  assert_eq!(restored.validate(), Ok(()));
+
+  // Words and values which disagree are rejected rather than leaving slots uninitialized
+  let json = r#"{"len":3,"bitmap":{"base":[5],"max_layers":3},"values":[1]}"#;
+  assert!(serde_json::from_str::<Pond<u64>>(json).is_err());
+  let json = r#"{"len":2,"bitmap":{"base":[5],"max_layers":3},"values":[1,2]}"#;
+  assert!(serde_json::from_str::<Pond<u64>>(json).is_err());
+}
+#[cfg(feature = "serde")]
+#[test]
+fn serialized_size() {
+  #[derive(serde::Serialize)]
+  enum Shape { Point, Circle(f32), Rect { w: u16, h: u16 } }
+
+  let mut pool = Pond::new();
+  let _ = pool.insert( (String::from("point"), Shape::Point, 'x') );
+  let _ = pool.insert( (String::from("circle"), Shape::Circle(2.0), 'é') );
+  pool.write(5, (String::new(), Shape::Rect { w: 1, h: 2 }, '🐸'));
+  pool.free(0);
+  assert_eq!(pool.serialized_size().unwrap(), bincode::serialize(&pool).unwrap().len());
+
+  let mut nested: Pond<Vec<Option<u64>>> = Pond::new();
+  nested.write(3, vec![Some(1), None]);
+  assert_eq!(nested.serialized_size().unwrap(), bincode::serialize(&nested).unwrap().len());
+}
+
+#[test]
+fn refcounting() {
+  use std::rc::Rc;
+  // Rc's strong count shows how many copies of the value are still alive
+  let tracker = Rc::new(());
+  let mut pool = Pond::with_refcounting();
+  let idx = pool.insert(Rc::clone(&tracker));
+  assert_eq!(pool.handle_count(idx), Some(1));
+  assert_eq!(pool.clone_handle(idx), Some(idx));
+  assert_eq!(pool.clone_handle(idx), Some(idx));
+
+  assert!(pool.free_handle(idx).is_none());
+  assert!(pool.free_handle(idx).is_none());
+  assert_eq!(pool.handle_count(idx), Some(1));
+  assert_eq!(Rc::strong_count(&tracker), 2);
+  drop(pool.free_handle(idx));
+  assert_eq!(Rc::strong_count(&tracker), 1);
+  assert!(!pool.is_occupied(idx));
+  assert_eq!(pool.free_handle(idx), None);
+  assert_eq!(pool.clone_handle(idx), None);
+
+  // A reused slot starts back at a single handle
+  let idx = pool.insert(Rc::clone(&tracker));
+  assert_eq!(pool.handle_count(idx), Some(1));
+}
+
+#[test]
+fn trim_and_fixup() {
+  let mut pool = Pond::new();
+  let mut indices: Vec<usize> = (0 .. 20).map(|val| pool.insert(val)).collect();
+  for idx in (0 .. 20).step_by(2) { pool.free(idx); }
+  indices.retain(|idx| idx % 2 == 1);
+
+  let mut parent = indices[9];
+  pool.trim_and_fixup(indices.iter_mut().chain([&mut parent]));
+  assert_eq!(pool.len(), 10);
+  for (val, idx) in (1 .. 20).step_by(2).zip(&indices) { assert_eq!(pool.get(*idx), Some(&val)); }
+  assert_eq!(pool.get(parent), Some(&19));
+}
+
+#[test]
+fn chunks_occupied() {
+  let mut pool = Pond::new();
+  for val in 0 .. 150 { let _ = pool.insert(val); }
+  for idx in [3, 4, 60, 149] { pool.free(idx); }
+
+  assert_eq!(pool.occupied_ranges().collect::<Vec<_>>(), vec![0 .. 3, 5 .. 60, 61 .. 149]);
+  let chunks: Vec<_> = pool.chunks_occupied(32).map(|(start, chunk)| (start, chunk.len())).collect();
+  assert_eq!(chunks, vec![(0, 3), (5, 32), (37, 23), (61, 32), (93, 32), (125, 24)]);
+  for (start, chunk) in pool.chunks_occupied(32) {
+    for (offset, val) in chunk.iter().enumerate() { assert_eq!(pool.get(start + offset), Some(val)); }
+  }
+  assert_eq!(pool.chunks_occupied(1000).map(|(_, chunk)| chunk.len()).sum::<usize>(), pool.count());
+}
+
+#[test]
+fn with_layers() {
+  for layers in [0, 1, 5] {
+    let mut pool = Pond::with_layers(layers);
+    for val in 0 .. 70_000 { let _ = pool.insert(val); }
+    for idx in [69_999, 40_000, 65] { pool.free(idx); }
+    assert_eq!(pool.next_index(), 65);
+    assert_eq!(pool.insert(0), 65);
+    assert_eq!(pool.insert(0), 40_000);
+    assert_eq!(pool.insert(0), 69_999);
+    assert_eq!(pool.insert(0), 70_000);
+    assert_eq!(pool.validate(), Ok(()));
+  }
+}
+
+#[test]
+fn free_mask() {
+  let mut pool = Pond::new();
+  for val in 0 .. 200 { let _ = pool.insert(val); }
+  pool.free(64);
+  // Slots 0, 63, 64 (already free), and everything in 128 .. 192, plus bits well past the end
+  let mask = [1 | 1 << 63, 1, !0, 0, !0];
+
+  let freed = pool.free_mask(&mask);
+  assert_eq!(freed, [0, 63].into_iter().chain(128 .. 192).collect::<Vec<_>>());
+  assert_eq!(pool.count(), 200 - 1 - freed.len());
+  assert!(pool.is_occupied(1) && pool.is_occupied(65) && pool.is_occupied(192));
+  assert!(pool.is_range_free(128 .. 192));
+  assert_eq!(pool.next_index(), 0);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn free_mask_bookkeeping() {
+  let mut stacked = Pond::with_free_stack();
+  for val in 0 .. 10 { let _ = stacked.insert(val); }
+  // Frees in index order, so the last slot freed is the first reused, just like freeing them one at a time
+  stacked.free_mask(&[1 << 2 | 1 << 7]);
+  assert_eq!(stacked.peek_free(3), vec![7, 2, 10]);
+  assert_eq!(stacked.insert(0), 7);
+
+  let mut counted = Pond::with_refcounting();
+  for val in 0 .. 4 { let _ = counted.insert(val); }
+  counted.clone_handle(1);
+  counted.free_mask(&[0b10]);
+  assert_eq!(counted.handle_count(1), None);
+  // A value written over the freed slot starts again from a single handle
+  counted.write(1, 1);
+  assert_eq!(counted.handle_count(1), Some(1));
+  assert_eq!(counted.free_handle(1), Some(1));
+}
+
+#[test]
+fn overwrite_without_strict() {
+  let mut pool = Pond::new();
+  pool.write(2, 'a');
+  assert_eq!(pool.write(2, 'b'), Some('a'));
+  let mut strict = Pond::new_strict();
+  strict.write(2, 'a');
+  strict.set(2, 'b');
+  assert_eq!(strict.free(2), Some('b'));
+  strict.write(2, 'c');
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "occupied slot 2")]
+fn strict_overwrite() {
+  let mut pool = Pond::new_strict();
+  pool.write(2, 'a');
+  pool.write(2, 'b');
+}
+
+#[test]
+fn shift() {
+  let mut pool = Pond::new();
+  for val in 0 .. 10 { let _ = pool.insert(val); }
+  for idx in [0, 4, 5] { pool.free(idx); }
+
+  let remap = pool.shift(3);
+  assert_eq!(pool.len(), 13);
+  assert_eq!(remap.len(), 7);
+  for (old, new) in &remap {
+    assert_eq!(*new, old + 3);
+    assert_eq!(pool.get(*new), Some(old));
+  }
+  assert!(pool.is_range_free(0 .. 4));
+  assert!(pool.is_range_free(7 .. 9));
+  assert_eq!(pool.count(), 7);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn ensure_free() {
+  let mut cache = Pond::with_eviction(10);
+  for val in 0 .. 6 { let _ = cache.insert(val); }
+  cache.free(2);
+
+  assert!(cache.ensure_free(5));
+  assert_eq!(cache.len(), 10);
+  assert!(!cache.ensure_free(6));
+  for val in 0 .. 5 { assert_eq!(cache.insert_evicting(val).1, None); }
+  assert!(!cache.ensure_free(1));
+  assert!(cache.ensure_free(0));
+
+  let mut pool = Pond::new();
+  let _ = pool.insert(0);
+  assert!(pool.ensure_free(100));
+  assert_eq!(pool.len(), 101);
+}
+
+#[test]
+fn snapshot_restore() {
+  let mut pool = Pond::new();
+  for val in 0 .. 100 { let _ = pool.insert(val.to_string()); }
+  for idx in (0 .. 100).step_by(7) { pool.free(idx); }
+  let expected: Vec<_> = pool.iter().map(|(idx, val)| (idx, val.clone())).collect();
+  let snapshot = pool.snapshot();
+  assert_eq!(snapshot.len(), 100);
+
+  for idx in 0 .. 50 { pool.free(idx); }
+  pool.write(300, String::from("new"));
+  let _ = pool.defrag();
+  pool.restore(&snapshot);
+
+  let restored: Vec<_> = pool.iter().map(|(idx, val)| (idx, val.clone())).collect();
+  assert_eq!(restored, expected);
+  assert_eq!(pool.len(), 100);
+  assert_eq!(pool.count(), expected.len());
+  assert!(pool.capacity() >= 301);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn as_ptr() {
+  let mut pool = Pond::new();
+  for val in 0 .. 100u32 { let _ = pool.insert(val * 3); }
+  pool.free(40);
+  let ptr = pool.as_ptr();
+  let words = pool.occupancy_words();
+  for idx in [0, 39, 41, 99] {
+    assert_eq!(words[idx / 64] >> (idx % 64) & 1, 1);
+    let read = unsafe { (*ptr.add(idx)).assume_init_ref() };
+    assert_eq!(Some(read), pool.get(idx));
+  }
+  assert_eq!(words[0] >> 40 & 1, 0);
+}
+
+#[test]
+fn compacted() {
+  let mut pool = Pond::with_metadata::<u8>();
+  for val in 0 .. 150 { let _ = pool.insert(val); }
+  for idx in (0 .. 150).filter(|idx| idx % 4 == 1) { pool.free(idx); }
+
+  let (packed, remap) = pool.compacted();
+  assert_eq!(packed.len(), pool.count());
+  assert!(packed.is_range_occupied(0 .. packed.len()));
+  let source: Vec<_> = pool.iter().map(|(_, val)| *val).collect();
+  let copied: Vec<_> = packed.iter().map(|(_, val)| *val).collect();
+  assert_eq!(copied, source);
+  for (old, new) in &remap { assert_eq!(packed.get(*new), pool.get(*old)); }
+  assert_eq!(pool.count(), 150 - 38);
+  assert_eq!(packed.validate(), Ok(()));
+}
+
+#[test]
+fn iter_from() {
+  let mut pool = Pond::new();
+  for val in 0 .. 10 { let _ = pool.insert(val); }
+  for idx in [0, 3, 9] { pool.free(idx); }
+
+  let visit = |start| pool.iter_from(start).map(|(idx, _)| idx).collect::<Vec<_>>();
+  assert_eq!(visit(0), vec![1, 2, 4, 5, 6, 7, 8]);
+  assert_eq!(visit(3), vec![4, 5, 6, 7, 8, 1, 2]);
+  assert_eq!(visit(5), vec![5, 6, 7, 8, 1, 2, 4]);
+  assert_eq!(visit(100), vec![1, 2, 4, 5, 6, 7, 8]);
+}
+
+#[test]
+fn contiguous_slice() {
+  let mut pool = Pond::new();
+  assert_eq!(pool.as_contiguous_slice(), Some(&[][..]));
+  for val in 0 .. 10 { let _ = pool.insert(val); }
+  pool.free(4);
+  assert!(pool.as_contiguous_slice_mut().is_none());
+
+  let _ = pool.trim();
+  for val in pool.as_contiguous_slice_mut().unwrap() { *val *= 2; }
+  assert_eq!(pool.get(4), Some(&18));
+  assert_eq!(pool.as_contiguous_slice(), Some(&[0, 2, 4, 6, 18, 10, 12, 14, 16][..]));
+  // Free slots past the end don't count as holes
+  pool.free(8);
+  assert_eq!(pool.as_contiguous_slice().map(<[_]>::len), Some(8));
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "slot 12, which is out of bounds for a pond of length 10")]
+fn strict_out_of_bounds_free() {
+  let mut pool = Pond::new_strict();
+  pool.write(9, 'a');
+  assert_eq!(pool.free(3), None);
+  pool.free(12);
+}
+
+#[test]
+fn trailing_free() {
+  let mut pool = Pond::new();
+  assert_eq!(pool.trailing_free(), 0);
+  pool.resize(100_000);
+  assert_eq!(pool.trailing_free(), 100_000);
+  pool.write(3, 'a');
+  pool.write(70, 'b');
+  assert_eq!(pool.trailing_free(), 100_000 - 71);
+  pool.free(70);
+  assert_eq!(pool.trailing_free(), 100_000 - 4);
+  pool.shrink_to_occupancy();
+  assert_eq!(pool.trailing_free(), 0);
+  assert_eq!(pool.len(), 4);
+}
+
+#[test]
+fn insert_unique() {
+  let mut pool = Pond::new();
+  let a = pool.insert_unique("a");
+  let b = pool.insert_unique("b");
+  assert_eq!(pool.insert_unique("a"), a);
+  assert_eq!(pool.len(), 2);
+
+  let mut index = std::collections::HashMap::new();
+  let c = pool.insert_unique_indexed("c", &mut index);
+  assert_eq!(pool.insert_unique_indexed("c", &mut index), c);
+  assert_eq!(pool.count(), 3);
+  // A stale entry doesn't hand back a slot which has moved on
+  pool.free(c);
+  pool.write(c, "d");
+  let c = pool.insert_unique_indexed("c", &mut index);
+  assert_eq!(pool.get(c), Some(&"c"));
+  assert_eq!(pool.count(), 4);
+  assert_ne!(b, c);
+}
+
+#[test]
+fn iter_mut_is_send() {
+  fn assert_send<I: Send>(_: &I) {}
+  let mut pool = Pond::new();
+  for idx in 0 .. 100 { let _ = pool.insert(idx); }
+  pool.free(50);
+  let iter = pool.iter_mut();
+  assert_send(&iter);
+  let (evens, odds): (Vec<_>, Vec<_>) = iter.partition(|(idx, _)| idx % 2 == 0);
+  std::thread::scope(|scope| {
+    for half in [evens, odds] {
+      scope.spawn(move || for (_, val) in half { *val *= 2 });
+    }
+  });
+  assert_eq!(pool.get(49), Some(&98));
+  assert_eq!(pool.iter().map(|(_, val)| *val).sum::<i32>(), (4950 - 50) * 2);
+}
+
+#[test]
+fn occupancy_mask() {
+  let mut pool = Pond::new();
+  pool.write(0, 'a');
+  pool.write(65, 'b');
+  pool.write(130, 'c');
+  let mask = pool.occupancy_mask();
+  pool.free(0);
+  pool.write(1, 'd');
+  assert_eq!(mask, vec![1, 1 << 1, 1 << 2]);
+  for idx in 0 .. 131 {
+    let bit = mask[idx / 64] >> (idx % 64) & 1 == 1;
+    assert_eq!(bit, [0, 65, 130].contains(&idx));
+  }
+}
+
+#[test]
+fn reserve_uninit() {
+  let mut pool = Pond::new();
+  let _ = pool.insert([1u8; 4096]);
+  let (idx, slot) = unsafe { pool.reserve_uninit() };
+  let big = slot.write([0; 4096]);
+  big[7] = 9;
+  assert_eq!(idx, 1);
+  assert_eq!(pool.get(idx).unwrap()[7], 9);
+  assert_eq!(pool.count(), 2);
+}
+
+#[test]
+fn set_occupancy_raw() {
+  let mut pool = Pond::new();
+  for idx in 0 .. 70 { pool.write(idx, idx); }
+  // Keeps 0 .. 3 and 64, and tries to claim slots past the end
+  unsafe { pool.set_occupancy_raw(&[0b111, 1, u64::MAX]) };
+  assert!(pool.is_occupied(2));
+  assert!(!pool.is_occupied(3));
+  assert_eq!(pool.get(64), Some(&64));
+  assert_eq!(pool.next_index(), 3);
+  assert_eq!(pool.count(), 4);
+  assert_eq!(pool.high_water_mark(), 70);
+  assert_eq!(pool.len(), 70);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn drain_range() {
+  let mut pool = Pond::new();
+  for idx in 0 .. 10 { pool.write(idx, idx * 10); }
+  pool.free(4);
+  let drained: Vec<_> = pool.drain_range(3 .. 7).collect();
+  assert_eq!(drained, vec![(3, 30), (5, 50), (6, 60)]);
+  assert_eq!(pool.count(), 6);
+  assert_eq!(pool.get(2), Some(&20));
+  assert_eq!(pool.get(7), Some(&70));
+
+  // Dropping partway through still frees the rest of the range
+  let mut drain = pool.drain_range(0 .. 100);
+  assert_eq!(drain.next(), Some((0, 0)));
+  drop(drain);
+  assert_eq!(pool.count(), 0);
+  assert_eq!(pool.len(), 10);
+}
+
+#[test]
+fn collect_occupied_into() {
+  let mut pool = Pond::new();
+  for idx in [1, 4, 9] { pool.write(idx, idx * 2); }
+  let mut out = vec![(0, &0); 16];
+  pool.collect_occupied_into(&mut out);
+  assert_eq!(out, vec![(1, &2), (4, &8), (9, &18)]);
+  assert!(out.capacity() >= 16);
+}
+
+#[test]
+fn replace_all() {
+  use std::rc::Rc;
+  let old = Rc::new(());
+  let mut pool = Pond::new();
+  for idx in [0, 3, 5, 7] { pool.write(idx, Rc::clone(&old)); }
+  let capacity = pool.capacity();
+
+  let new = Rc::new(());
+  pool.replace_all(std::iter::repeat_with(|| Rc::clone(&new)).take(6));
+  assert_eq!(Rc::strong_count(&old), 1);
+  assert_eq!(Rc::strong_count(&new), 7);
+  assert_eq!(pool.len(), 6);
+  assert_eq!(pool.count(), 6);
+  assert!(pool.is_range_occupied(0 .. 6));
+  assert_eq!(pool.capacity(), capacity);
+  assert_eq!(pool.validate(), Ok(()));
+
+  pool.replace_all([]);
+  assert_eq!(Rc::strong_count(&new), 1);
+  assert!(pool.is_empty());
+}
+
+#[test]
+fn get_mut_or_fill() {
+  let mut pool: Pond<u32> = Pond::new();
+  *pool.get_mut_or_fill(5) += 7;
+  assert_eq!(pool.count(), 6);
+  assert_eq!(pool.iter().map(|(_, val)| *val).collect::<Vec<_>>(), vec![0, 0, 0, 0, 0, 7]);
+  // Holes inside the pond are filled alone
+  pool.free(2);
+  pool.free(4);
+  assert_eq!(*pool.get_mut_or_fill(2), 0);
+  assert!(!pool.is_occupied(4));
+  assert_eq!(*pool.get_mut_or_fill(5), 7);
+}
+
+#[test]
+fn is_packed() {
+  let mut pool = Pond::new();
+  assert!(pool.is_packed());
+  for idx in 0 .. 64 { pool.write(idx, idx); }
+  assert!(pool.is_packed());
+  pool.free(10);
+  assert!(!pool.is_packed());
+  assert_eq!(pool.as_contiguous_slice(), None);
+  let _ = pool.trim();
+  assert!(pool.is_packed());
+  // Free space after the values doesn't count as a hole
+  pool.resize(200);
+  assert!(pool.is_packed());
+  assert_eq!(pool.as_contiguous_slice().map(|slice| slice.len()), Some(63));
+}
+
+#[test]
+fn reserve_block_default() {
+  let mut pool: Pond<u8> = Pond::new();
+  for idx in [0, 2, 6] { pool.write(idx, 1); }
+  // 3 .. 6 is the first gap wide enough
+  let (start, block) = pool.reserve_block_default(3);
+  assert_eq!(start, 3);
+  assert_eq!(block.len(), 3);
+  block.copy_from_slice(&[7, 8, 9]);
+  assert!(pool.is_range_occupied(2 .. 7));
+  assert_eq!(pool.get(4), Some(&8));
+  // No gap is wide enough, so it lands on the end
+  let (start, block) = pool.reserve_block_default(2);
+  assert_eq!((start, block.len()), (7, 2));
+  assert_eq!(pool.len(), 9);
+  assert_eq!(pool.count(), 8);
+}
+
+#[test]
+fn apply_permutation() {
+  let mut pool = Pond::new();
+  for (idx, val) in ['a', 'b', 'c', 'd', 'e'].into_iter().enumerate() { pool.write(idx, val); }
+  pool.resize(7);
+  pool.free(1);
+  // A 3-cycle, a swap with a free slot, and a fixed point
+  pool.apply_permutation(&[2, 5, 4, 3, 0, 1, 6]);
+  let placed: Vec<_> = pool.iter().map(|(idx, val)| (idx, *val)).collect();
+  assert_eq!(placed, vec![(0, 'e'), (2, 'a'), (3, 'd'), (4, 'c')]);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "isn't a valid target")]
+fn apply_permutation_duplicate() {
+  let mut pool = Pond::new();
+  pool.write(2, 'a');
+  pool.apply_permutation(&[1, 1, 0]);
+}
+
+#[test]
+fn debug_occupancy_string() {
+  let mut pool = Pond::new();
+  for idx in [0, 1, 5, 8, 17] { pool.write(idx, ()); }
+  pool.resize(19);
+  let drawn = pool.debug_occupancy_string();
+  assert_eq!(drawn, "##...#.. #....... .#.");
+  let slots: Vec<char> = drawn.chars().filter(|c| *c != ' ').collect();
+  assert_eq!(slots.len(), pool.len());
+  for (idx, slot) in slots.into_iter().enumerate() { assert_eq!(slot == '#', pool.is_occupied(idx)) }
+}
+
+#[test]
+#[should_panic(expected = "past the largest index a pond can hold")]
+fn index_overflow() {
+  let mut pool = Pond::new();
+  pool.write(3, ());
+  // Would wrap around to resizing to 0 in release builds
+  pool.write(usize::MAX, ());
+}
+
+#[test]
+fn zip_occupied() {
+  let mut positions = Pond::new();
+  let mut velocities = Pond::new();
+  for idx in [0, 3, 64, 70, 200] { positions.write(idx, idx as f32); }
+  for idx in [3, 4, 70, 200, 300] { velocities.write(idx, -(idx as i32)); }
+  velocities.free(200);
+  let joined: Vec<_> = positions.zip_occupied(&velocities).map(|(idx, pos, vel)| (idx, *pos, *vel)).collect();
+  assert_eq!(joined, vec![(3, 3.0, -3), (70, 70.0, -70)]);
+}
+
+#[test]
+fn builder() {
+  let mut pool = Pond::builder().layers(0).capacity(100).refcounting().max_capacity(3).build();
+  assert!(pool.capacity() >= 100);
+  for val in 0 .. 3 { let _ = pool.insert(val); }
+  // The cap evicts the oldest rather than growing
+  assert_eq!(pool.insert_evicting(3), (0, Some(0)));
+  assert_eq!(pool.len(), 3);
+  assert_eq!(pool.handle_count(1), Some(1));
+
+  // Layers show up in the bitmap's footprint
+  let sized = |mut pool: Pond<u8>| { pool.resize(64 * 40); pool.shrink_to_fit(); pool.memory_usage() };
+  assert_eq!(sized(Pond::builder().layers(0).build()), sized(Pond::with_layers(0)));
+  assert_ne!(sized(Pond::builder().layers(0).build()), sized(Pond::new()));
+
+  let mut tagged = Pond::<char>::builder().recency().metadata::<u8>().build();
+  let idx = tagged.insert_tagged('a', 5);
+  assert_eq!(tagged.meta(idx), Some(&5));
+  assert_eq!(tagged.iter_by_recency().count(), 1);
+}
+
+#[test]
+fn insert_or_get() {
+  let mut pool = Pond::new();
+  let mut index = std::collections::HashMap::new();
+  let (hello, new) = pool.insert_or_get(String::from("hello"), &mut index);
+  assert!(new);
+  let (world, _) = pool.insert_or_get(String::from("world"), &mut index);
+  for _ in 0 .. 3 { assert_eq!(pool.insert_or_get(String::from("hello"), &mut index), (hello, false)) }
+  assert_ne!(hello, world);
+  assert_eq!(pool.count(), 2);
+}
+
+#[test]
+fn free_stack() {
+  let mut pool = Pond::with_free_stack();
+  for val in 0 .. 10 { let _ = pool.insert(val); }
+  pool.free(2);
+  pool.free(7);
+  pool.free(4);
+  // Most recently freed first, rather than lowest first
+  assert_eq!(pool.insert(40), 4);
+  // A stale entry is skipped once its slot gets filled some other way
+  pool.write(7, 70);
+  assert_eq!(pool.insert(20), 2);
+  assert_eq!(pool.insert(10), 10);
+  pool.free(9);
+  pool.free(3);
+  let _ = pool.trim();
+  assert_eq!(pool.len(), 9);
+  assert_eq!(pool.insert(90), 9);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn preview_trim() {
+  let mut pool = Pond::new();
+  for val in 0 .. 300 { let _ = pool.insert(val); }
+  for idx in (0 .. 300).filter(|idx| idx % 3 == 0 || (100 .. 180).contains(idx)) { pool.free(idx); }
+  let preview = pool.preview_trim();
+  let before: Vec<(usize, i32)> = pool.iter().map(|(idx, val)| (idx, *val)).collect();
+  assert_eq!(pool.trim(), preview);
+  for (idx, val) in before { assert_eq!(pool.get(*preview.get(&idx).unwrap_or(&idx)), Some(&val)) }
+  assert!(pool.preview_trim().is_empty());
+}
+
+#[test]
+fn get_many_sorted() {
+  let mut pool = Pond::new();
+  for idx in (0 .. 500).step_by(3) { pool.write(idx, idx * 2); }
+  let indices: Vec<usize> = (0 .. 600).step_by(2).collect();
+  let expected: Vec<_> = indices.iter().map(|idx| pool.get(*idx)).collect();
+  assert_eq!(pool.get_many_sorted(&indices).collect::<Vec<_>>(), expected);
+  assert_eq!(pool.get_many_sorted(&[3, 4, 999]).collect::<Vec<_>>(), vec![Some(&6), None, None]);
+}
+
+#[test]
+fn raw_parts() {
+  let mut pool = Pond::new();
+  for idx in [0, 2, 65, 100] { pool.write(idx, idx.to_string()); }
+  pool.resize(130);
+  let (data, words) = pool.into_raw_parts();
+  assert_eq!(data.len(), 130);
+  assert_eq!(words, vec![0b101, 1 << 1 | 1 << 36, 0]);
+
+  let pool = unsafe { Pond::<String>::from_raw_parts(data, words) };
+  assert_eq!(pool.len(), 130);
+  assert_eq!(pool.count(), 4);
+  assert_eq!(pool.iter().map(|(idx, val)| (idx, val.as_str())).collect::<Vec<_>>(), vec![(0, "0"), (2, "2"), (65, "65"), (100, "100")]);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn insert_after_hinted_run() {
+  let mut pool = Pond::new();
+  for val in 0 .. 200 { assert_eq!(pool.insert(val), val); }
+  // Anything which frees a slot behind the last insert has to be found again
+  pool.free(150);
+  assert_eq!(pool.insert(0), 150);
+  pool.free_mask(&[0, 1 << 6]);
+  assert_eq!(pool.insert(0), 70);
+  pool.free(5);
+  pool.resize(3);
+  assert_eq!(pool.insert(0), 3);
+  let mut perm: Vec<usize> = (0 .. 4).collect();
+  perm.swap(0, 3);
+  pool.free(3);
+  pool.apply_permutation(&perm);
+  assert_eq!(pool.insert(0), 0);
+  unsafe { pool.set_occupancy_raw(&[0b1011]) };
+  assert_eq!(pool.insert(0), 2);
+  assert_eq!(pool.validate(), Ok(()));
+}
+
+#[test]
+fn reset() {
+  use std::rc::Rc;
+  let shared = Rc::new(());
+  let mut pool = Pond::with_metadata::<u8>();
+  for idx in [1, 4, 40] { pool.write(idx, Rc::clone(&shared)); }
+  *pool.meta_mut(4).unwrap() = 9;
+  let capacity = pool.capacity();
+  pool.reset();
+  assert_eq!(Rc::strong_count(&shared), 1);
+  assert_eq!(pool.len(), 41);
+  assert_eq!(pool.capacity(), capacity);
+  assert_eq!(pool.count(), 0);
+  assert!(pool.is_range_free(0 .. 41));
+  pool.write(4, Rc::clone(&shared));
+  assert_eq!(pool.len(), 41);
+  assert_eq!(pool.meta(4), Some(&0));
+}
+
+#[test]
+fn iter_with_meta() {
+  let mut pool = Pond::with_metadata::<u32>();
+  for val in 0 .. 10 { let _ = pool.insert_tagged(val, val as u32 * 100); }
+  for idx in [1, 2, 5] { pool.free(idx); }
+  let _ = pool.defrag();
+  let joined: Vec<_> = pool.iter_with_meta().map(|(_, val, meta)| (*val, *meta)).collect();
+  assert_eq!(joined.len(), 7);
+  for (val, meta) in joined { assert_eq!(meta, val as u32 * 100) }
+}
+
+#[test]
+fn recency_memory() {
+  let mut plain = Pond::new();
+  let mut recent = Pond::with_recency();
+  for val in 0 .. 10_000u16 { let _ = plain.insert(val); let _ = recent.insert(val); }
+  assert!(recent.memory_usage() >= plain.memory_usage() + 10_000 * 8);
+  for idx in 100 .. 10_000 { plain.free(idx); recent.free(idx); }
+  plain.shrink_to_occupancy();
+  recent.shrink_to_occupancy();
+  plain.shrink_to_fit();
+  recent.shrink_to_fit();
+  assert!(recent.memory_usage() - plain.memory_usage() < 1_000);
+  assert_eq!(recent.iter_by_recency().count(), 100);
+}
+
+#[test]
+fn refcount_memory() {
+  let mut plain = Pond::new();
+  let mut counted = Pond::with_refcounting();
+  for val in 0 .. 10_000u16 { let _ = plain.insert(val); let _ = counted.insert(val); }
+  assert!(counted.memory_usage() >= plain.memory_usage() + 10_000 * 4);
+  for idx in 100 .. 10_000 { plain.free(idx); counted.free(idx); }
+  plain.shrink_to(200);
+  counted.shrink_to(200);
+  assert!(counted.memory_usage() - plain.memory_usage() < 1_000);
+  assert_eq!(counted.handle_count(99), Some(1));
+}
+
+#[test]
+fn free_stack_memory() {
+  let mut plain = Pond::new();
+  let mut stacked = Pond::with_free_stack();
+  for val in 0 .. 10_000u16 { let _ = plain.insert(val); let _ = stacked.insert(val); }
+  for idx in 100 .. 10_000 { plain.free(idx); stacked.free(idx); }
+  assert!(stacked.memory_usage() >= plain.memory_usage() + 9_900 * 8);
+  plain.shrink_to(200);
+  stacked.shrink_to(200);
+  // Only the entries which still point inside the pond survive the shrink
+  assert!(stacked.memory_usage() - plain.memory_usage() <= 200 * 8);
+  assert_eq!(stacked.insert(0), 199);
+}
+
+#[test]
+#[should_panic(expected = "past the largest index a pond can hold")]
+fn shift_overflow() {
+  let mut pool = Pond::new();
+  pool.write(3, 'a');
+  // Would wrap around to truncating the pond in release builds
+  let _ = pool.shift(usize::MAX - 2);
+}
+
+#[test]
+fn set_occupancy_raw_refcounts() {
+  let mut pool = Pond::with_refcounting();
+  for idx in 0 .. 3 { pool.write(idx, idx); }
+  pool.clone_handle(0);
+  unsafe { pool.set_occupancy_raw(&[0b001]) };
+  // Slot 2's value is still there, just forgotten, so it can be picked back up
+  unsafe { pool.set_occupancy_raw(&[0b101]) };
+  assert_eq!(pool.handle_count(0), Some(2));
+  assert_eq!(pool.handle_count(2), Some(1));
+  assert_eq!(pool.free_handle(2), Some(2));
+}
+
+#[test]
+fn free_stack_next_index() {
+  let mut pool = Pond::with_free_stack();
+  for val in 0 .. 10 { let _ = pool.insert(val); }
+  pool.free(2);
+  pool.free(7);
+  pool.free(4);
+  // Slot 4 went back in without popping, so its entry is stale
+  pool.write(4, 4);
+  assert_eq!(pool.next_index(), 7);
+  assert_eq!(pool.peek_free(4), vec![7, 2, 10, 11]);
+  assert_eq!(pool.reserve_slot().index(), 7);
+  assert_eq!(pool.insert(0), 7);
+  assert_eq!(pool.next_index(), 2);
+  assert_eq!(pool.insert(0), 2);
+  assert_eq!(pool.next_index(), 10);
+  assert_eq!(pool.insert(0), 10);
+}